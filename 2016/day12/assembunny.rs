@@ -0,0 +1,701 @@
+// SPDX-FileCopyrightText: 2024 - 2025 Eli Array Minkoff
+//
+// SPDX-License-Identifier: 0BSD
+
+//! Module providing an Assembunny interpreter, as used in 2016 days 12, 23 and 25, which can be
+//! constructed with [`Vm::new`].
+//!
+//! It supports the union of every day's instruction set: `cpy`, `inc`, `dec` and `jnz` from day
+//! 12, `tgl` from day 23, and `out` from day 25.
+//!
+//! # Invalid instructions
+//!
+//! Source code is expected to be valid, so parsing rejects instructions such as `inc 1` or
+//! `cpy a 2`. However, `tgl` can turn valid instructions into invalid ones, such as turning
+//! `jnz 1 c` into `cpy 1 c`, which is fine, but also `jnz 1 2` into `cpy 1 2`, which is not. As the
+//! puzzle describes, any instruction that's invalid after being toggled is skipped when it's
+//! executed, though it can be toggled back into a valid instruction later.
+//!
+//! The program halts when the program counter leaves the program, whether by running past the end
+//! or by jumping to before the start.
+//!
+//! # Optimization
+//!
+//! Programs tend to multiply by repeatedly incrementing a register in a pair of nested loops,
+//! which can take billions of instructions. With [`Vm::enable_optimizer`], recognized loops are
+//! replaced by a [`Fused`] pseudo-instruction, which is recognized again whenever `tgl` modifies
+//! any instruction within it, so it's never used once the loop it replaced has changed.
+//!
+//! # Example
+//! ```rust
+//! use assembunny::{RegId, Vm};
+//! let code = assembunny::parse("cpy 2 a\nout a\ndec a\njnz a -2").unwrap();
+//! let mut vm = Vm::new(code);
+//! assert_eq!(vm.outputs().collect::<Vec<_>>(), [2, 1]);
+//! assert_eq!(vm.regs[RegId::A], 0);
+//! ```
+
+#![cfg_attr(
+    aoc_direct,
+    allow(dead_code, reason = "file written as standalone crate")
+)]
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A line of Assembunny that couldn't be parsed
+#[derive(Debug, PartialEq, Clone)]
+pub struct AssembunnyParseError {
+    /// The 1-indexed line number, or 0 if parsing a single instruction
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for AssembunnyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: invalid instruction {:?}", self.line, self.text)
+    }
+}
+
+impl std::error::Error for AssembunnyParseError {}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum RegId {
+    A,
+    B,
+    C,
+    D,
+}
+
+impl FromStr for RegId {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "a" => Ok(RegId::A),
+            "b" => Ok(RegId::B),
+            "c" => Ok(RegId::C),
+            "d" => Ok(RegId::D),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for RegId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RegId::A => "a",
+            RegId::B => "b",
+            RegId::C => "c",
+            RegId::D => "d",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Param {
+    RegMode(RegId),
+    Imm(i64),
+}
+
+impl FromStr for Param {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(r) = RegId::from_str(s) {
+            Ok(Param::RegMode(r))
+        } else {
+            s.parse().map(Param::Imm).map_err(|_| ())
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Param::RegMode(r) => write!(f, "{r}"),
+            Param::Imm(i) => write!(f, "{i}"),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone)]
+pub struct Regs {
+    pub a: i64,
+    pub b: i64,
+    pub c: i64,
+    pub d: i64,
+}
+
+impl std::ops::Index<RegId> for Regs {
+    type Output = i64;
+    fn index(&self, reg_id: RegId) -> &i64 {
+        match reg_id {
+            RegId::A => &self.a,
+            RegId::B => &self.b,
+            RegId::C => &self.c,
+            RegId::D => &self.d,
+        }
+    }
+}
+
+impl std::ops::IndexMut<RegId> for Regs {
+    fn index_mut(&mut self, reg_id: RegId) -> &mut i64 {
+        match reg_id {
+            RegId::A => &mut self.a,
+            RegId::B => &mut self.b,
+            RegId::C => &mut self.c,
+            RegId::D => &mut self.d,
+        }
+    }
+}
+
+impl Regs {
+    pub fn value_of(&self, param: Param) -> i64 {
+        match param {
+            Param::RegMode(reg_id) => self[reg_id],
+            Param::Imm(i) => i,
+        }
+    }
+}
+
+/// An Assembunny instruction. Every operand is a [`Param`], as `tgl` can leave immediate values
+/// where registers are expected - see the [module-level documentation](self) for how those are
+/// handled.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Instruction {
+    Cpy(Param, Param),
+    Inc(Param),
+    Dec(Param),
+    Jnz(Param, Param),
+    Tgl(Param),
+    Out(Param),
+}
+
+impl Instruction {
+    /// Toggle the instruction, as done by `tgl`
+    pub fn toggle(&mut self) {
+        use Instruction as I;
+        *self = match *self {
+            I::Inc(x) => I::Dec(x),
+            I::Dec(x) | I::Tgl(x) | I::Out(x) => I::Inc(x),
+            I::Jnz(x, y) => I::Cpy(x, y),
+            I::Cpy(x, y) => I::Jnz(x, y),
+        };
+    }
+
+    /// Whether the instruction can be executed, rather than being skipped
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Instruction::Cpy(_, y) | Instruction::Inc(y) | Instruction::Dec(y) => {
+                matches!(y, Param::RegMode(_))
+            }
+            Instruction::Jnz(..) | Instruction::Tgl(_) | Instruction::Out(_) => true,
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = AssembunnyParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = s.split_whitespace().collect();
+        let instruction = match &words[..] {
+            ["cpy", x, y] => x.parse().and_then(|x| Ok(Instruction::Cpy(x, y.parse()?))),
+            ["inc", x] => x.parse().map(Instruction::Inc),
+            ["dec", x] => x.parse().map(Instruction::Dec),
+            ["jnz", x, y] => x.parse().and_then(|x| Ok(Instruction::Jnz(x, y.parse()?))),
+            ["tgl", x] => x.parse().map(Instruction::Tgl),
+            ["out", x] => x.parse().map(Instruction::Out),
+            _ => Err(()),
+        };
+        instruction
+            .ok()
+            .filter(Instruction::is_valid)
+            .ok_or_else(|| AssembunnyParseError {
+                line: 0,
+                text: s.to_string(),
+            })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Cpy(x, y) => write!(f, "cpy {x} {y}"),
+            Instruction::Inc(x) => write!(f, "inc {x}"),
+            Instruction::Dec(x) => write!(f, "dec {x}"),
+            Instruction::Jnz(x, y) => write!(f, "jnz {x} {y}"),
+            Instruction::Tgl(x) => write!(f, "tgl {x}"),
+            Instruction::Out(x) => write!(f, "out {x}"),
+        }
+    }
+}
+
+/// Parse a program, with one instruction per line
+pub fn parse(src: &str) -> Result<Vec<Instruction>, AssembunnyParseError> {
+    src.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            line.parse()
+                .map_err(|e| AssembunnyParseError { line: i + 1, ..e })
+        })
+        .collect()
+}
+
+/// What happened when a single instruction was executed
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Step {
+    Continue,
+    Output(i64),
+    Halted,
+}
+
+/// A pseudo-instruction that replaces a recognized block of instructions
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Fused {
+    /// Add `src * outer` to `dest`, leaving `counter` and `outer` at 0, in place of the nested
+    /// loops of `cpy src counter`, `inc dest`, `dec counter`, `jnz counter -2`, `dec outer` and
+    /// `jnz outer -5`, with `inc dest` and `dec counter` in either order
+    MulAdd {
+        src: Param,
+        dest: RegId,
+        counter: RegId,
+        outer: RegId,
+    },
+}
+
+/// The most instructions that a [`Fused`] pseudo-instruction can replace
+const MAX_FUSED_LEN: usize = 6;
+
+impl Fused {
+    /// The number of instructions replaced
+    pub fn replaced(&self) -> usize {
+        match self {
+            Fused::MulAdd { .. } => 6,
+        }
+    }
+
+    /// Recognize the block of instructions at the start of `code`
+    pub fn recognize(code: &[Instruction]) -> Option<Self> {
+        use Instruction as I;
+        use Param::{Imm, RegMode as R};
+        let (src, counter, dest, outer) = match *code.get(..MAX_FUSED_LEN)? {
+            [
+                I::Cpy(src, R(counter)),
+                I::Inc(R(dest)),
+                I::Dec(R(c1)),
+                I::Jnz(R(c2), Imm(-2)),
+                I::Dec(R(outer)),
+                I::Jnz(R(o), Imm(-5)),
+            ]
+            | [
+                I::Cpy(src, R(counter)),
+                I::Dec(R(c1)),
+                I::Inc(R(dest)),
+                I::Jnz(R(c2), Imm(-2)),
+                I::Dec(R(outer)),
+                I::Jnz(R(o), Imm(-5)),
+            ] if c1 == counter && c2 == counter && o == outer => (src, counter, dest, outer),
+            _ => return None,
+        };
+        let regs = [dest, counter, outer];
+        let distinct = dest != counter && dest != outer && counter != outer;
+        // src must stay the same while the loops run
+        if !distinct || matches!(src, R(r) if regs.contains(&r)) {
+            return None;
+        }
+        Some(Fused::MulAdd {
+            src,
+            dest,
+            counter,
+            outer,
+        })
+    }
+
+    /// Execute the pseudo-instruction, or return `false` without changing anything if the
+    /// instructions it replaced wouldn't have terminated normally, so they must be run literally
+    fn exec(self, regs: &mut Regs) -> bool {
+        match self {
+            Fused::MulAdd {
+                src,
+                dest,
+                counter,
+                outer,
+            } => {
+                let (src, times) = (regs.value_of(src), regs[outer]);
+                let Some(sum) = src
+                    .checked_mul(times)
+                    .and_then(|product| regs[dest].checked_add(product))
+                    .filter(|_| src > 0 && times > 0)
+                else {
+                    return false;
+                };
+                regs[dest] = sum;
+                regs[counter] = 0;
+                regs[outer] = 0;
+                true
+            }
+        }
+    }
+}
+
+/// An Assembunny virtual machine, which owns a copy of its program, as `tgl` can modify it
+#[derive(Debug, Clone)]
+pub struct Vm {
+    pub regs: Regs,
+    code: Vec<Instruction>,
+    pc: i64,
+    /// Pseudo-instructions, indexed by the address of the first instruction they replace, if the
+    /// optimizer is enabled
+    fused: Option<Vec<Option<Fused>>>,
+}
+
+// The optimizer doesn't affect the machine's state
+impl PartialEq for Vm {
+    fn eq(&self, other: &Self) -> bool {
+        self.regs == other.regs && self.code == other.code && self.pc == other.pc
+    }
+}
+
+impl Eq for Vm {}
+
+impl std::hash::Hash for Vm {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.regs.hash(state);
+        self.code.hash(state);
+        self.pc.hash(state);
+    }
+}
+
+impl Vm {
+    pub fn new(code: Vec<Instruction>) -> Self {
+        Self {
+            regs: Regs::default(),
+            code,
+            pc: 0,
+            fused: None,
+        }
+    }
+
+    /// Replace recognized blocks of instructions with faster pseudo-instructions
+    pub fn enable_optimizer(&mut self) {
+        self.fused = Some(
+            (0..self.code.len())
+                .map(|i| Fused::recognize(&self.code[i..]))
+                .collect(),
+        );
+    }
+
+    pub fn disable_optimizer(&mut self) {
+        self.fused = None;
+    }
+
+    /// The pseudo-instructions currently in use, along with the addresses of the first
+    /// instructions they replace
+    pub fn fused(&self) -> impl Iterator<Item = (usize, Fused)> + '_ {
+        self.fused
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter_map(|(i, fused)| Some((i, (*fused)?)))
+    }
+
+    /// The index of the next instruction to execute
+    pub fn pc(&self) -> i64 {
+        self.pc
+    }
+
+    /// The program, including any changes made by `tgl`
+    pub fn code(&self) -> &[Instruction] {
+        &self.code
+    }
+
+    fn current(&self) -> Option<Instruction> {
+        usize::try_from(self.pc)
+            .ok()
+            .and_then(|pc| self.code.get(pc))
+            .copied()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.current().is_none()
+    }
+
+    /// Execute a single instruction
+    pub fn step(&mut self) -> Step {
+        let Some(instr) = self.current() else {
+            return Step::Halted;
+        };
+        if let Some(fused) = self.fused.as_ref().and_then(|f| f[self.pc as usize])
+            && fused.exec(&mut self.regs)
+        {
+            self.pc += fused.replaced() as i64;
+            return Step::Continue;
+        }
+        let regs = &mut self.regs;
+        let mut next_pc = self.pc + 1;
+        let mut step = Step::Continue;
+        match instr {
+            Instruction::Cpy(x, Param::RegMode(y)) => regs[y] = regs.value_of(x),
+            Instruction::Inc(Param::RegMode(x)) => regs[x] += 1,
+            Instruction::Dec(Param::RegMode(x)) => regs[x] -= 1,
+            Instruction::Jnz(x, y) => {
+                if regs.value_of(x) != 0 {
+                    next_pc = self.pc + regs.value_of(y);
+                }
+            }
+            Instruction::Tgl(x) => {
+                if let Ok(i) = usize::try_from(self.pc + regs.value_of(x))
+                    && let Some(target) = self.code.get_mut(i)
+                {
+                    target.toggle();
+                    // recognize any block that includes the toggled instruction again
+                    if let Some(fused) = self.fused.as_mut() {
+                        let first = i.saturating_sub(MAX_FUSED_LEN - 1);
+                        for (start, slot) in (first..).zip(&mut fused[first..=i]) {
+                            *slot = Fused::recognize(&self.code[start..]);
+                        }
+                    }
+                }
+            }
+            Instruction::Out(x) => step = Step::Output(regs.value_of(x)),
+            // skip instructions made invalid by tgl
+            Instruction::Cpy(..) | Instruction::Inc(_) | Instruction::Dec(_) => (),
+        }
+        self.pc = next_pc;
+        step
+    }
+
+    /// Run until the next `out` instruction, returning its value, or until the program halts
+    pub fn resume(&mut self) -> Option<i64> {
+        loop {
+            match self.step() {
+                Step::Continue => (),
+                Step::Output(value) => return Some(value),
+                Step::Halted => return None,
+            }
+        }
+    }
+
+    /// Iterate over the values output by the program, pausing after each one, and ending once
+    /// the program halts
+    pub fn outputs(&mut self) -> impl Iterator<Item = i64> + '_ {
+        std::iter::from_fn(|| self.resume())
+    }
+
+    /// Run until the program halts, discarding any output, and return the final registers
+    pub fn run(&mut self) -> &Regs {
+        while self.resume().is_some() {}
+        &self.regs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn day12_example() {
+        let code = parse("cpy 41 a\ninc a\ninc a\ndec a\njnz a 2\ndec a").unwrap();
+        assert_eq!(Vm::new(code).run().a, 42);
+    }
+
+    #[test]
+    fn day23_example() {
+        let code = parse("cpy 2 a\ntgl a\ntgl a\ntgl a\ncpy 1 a\ndec a\ndec a").unwrap();
+        let mut vm = Vm::new(code);
+        assert_eq!(vm.run().a, 3);
+        assert_eq!(vm.code()[3], "inc a".parse().unwrap());
+        assert_eq!(vm.code()[4], "jnz 1 a".parse().unwrap());
+    }
+
+    #[test]
+    fn invalid_instructions() {
+        assert_eq!(
+            parse("inc a\ncpy a 2"),
+            Err(AssembunnyParseError {
+                line: 2,
+                text: "cpy a 2".into()
+            })
+        );
+        assert!(parse("inc 1").is_err());
+        assert!(parse("jnz a").is_err());
+
+        // `jnz 1 2` toggled into `cpy 1 2` is skipped, then toggled back and run the second time
+        // round the loop, so `inc a` only runs once
+        let code = parse("tgl 2\ncpy 2 c\njnz 1 2\ninc a\ndec c\ntgl -3\njnz c -4").unwrap();
+        let mut vm = Vm::new(code);
+        assert_eq!(
+            vm.run(),
+            &Regs {
+                a: 1,
+                ..Regs::default()
+            }
+        );
+        assert_eq!(vm.code()[2], Instruction::Cpy(Param::Imm(1), Param::Imm(2)));
+    }
+
+    #[test]
+    fn pausable_output() {
+        let code = parse("out 0\nout 1\njnz 1 -2").unwrap();
+        let mut vm = Vm::new(code);
+        assert_eq!(vm.outputs().take(5).collect::<Vec<_>>(), [0, 1, 0, 1, 0]);
+        assert_eq!(vm.pc(), 1);
+        assert_eq!(vm.resume(), Some(1));
+        assert!(!vm.is_halted());
+    }
+
+    #[test]
+    fn round_trip() {
+        let src = "cpy 41 a\ninc a\ndec b\njnz c -2\ntgl d\nout a";
+        let listing: Vec<String> = parse(src).unwrap().iter().map(|i| i.to_string()).collect();
+        assert_eq!(listing.join("\n"), src);
+    }
+
+    /// A program with the same structure as day 23's input, which calculates `a! + 3 * 4`, as long
+    /// as `a` is at least 6
+    const FACTORIAL: &str = "cpy a b\ndec b\ncpy a d\ncpy 0 a\ncpy b c\ninc a\ndec c\njnz c -2\n\
+                             dec d\njnz d -5\ndec b\ncpy b c\ncpy c d\ndec d\ninc c\njnz d -2\n\
+                             tgl c\ncpy -16 c\njnz 1 c\ncpy 3 c\njnz 4 d\ninc a\ninc d\njnz d -2\n\
+                             inc c\njnz c -5";
+
+    #[test]
+    fn optimizer_matches_interpreter() {
+        let code = parse(FACTORIAL).unwrap();
+        for a in 6..=9 {
+            let mut plain = Vm::new(code.clone());
+            plain.regs.a = a;
+            let mut optimized = plain.clone();
+            optimized.enable_optimizer();
+            assert_eq!(optimized.fused().map(|(i, _)| i).collect::<Vec<_>>(), [4]);
+            assert_eq!(plain.run(), optimized.run());
+            assert_eq!(plain.regs.a, (1..=a).product::<i64>() + 12);
+            assert_eq!(plain, optimized);
+        }
+
+        // too slow to run without the optimizer
+        let code =
+            parse("cpy 1000000 b\ncpy 1000000 d\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5");
+        let mut vm = Vm::new(code.unwrap());
+        vm.enable_optimizer();
+        assert_eq!(vm.run().a, 1_000_000_000_000);
+    }
+
+    #[test]
+    fn optimizer_invalidated_by_tgl() {
+        // `tgl 2` turns `inc a` into `dec a` before the loop is reached
+        let code =
+            parse("cpy 3 b\ncpy 4 d\ntgl 2\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5");
+        let mut vm = Vm::new(code.unwrap());
+        vm.enable_optimizer();
+        assert_eq!(vm.fused().count(), 1);
+        assert_eq!(vm.run().a, -12);
+        assert_eq!(vm.fused().count(), 0);
+
+        // loops that wouldn't terminate normally are left to run literally
+        let code = parse("cpy 0 b\ncpy 4 d\ncpy b c\ninc a\ndec c\njnz c -2\ndec d\njnz d -5");
+        let mut vm = Vm::new(code.unwrap());
+        vm.enable_optimizer();
+        for _ in 0..100 {
+            vm.step();
+        }
+        assert_eq!((vm.regs.a, vm.regs.d), (33, 4));
+    }
+
+    /// Generates random Assembunny programs and registers, from a 64-bit linear congruential
+    /// generator
+    struct ProgramGen(u64);
+
+    impl ProgramGen {
+        fn range(&mut self, range: std::ops::Range<i64>) -> i64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            // the high bits are the most random
+            range.start + ((self.0 >> 33) % (range.end - range.start) as u64) as i64
+        }
+
+        fn reg(&mut self) -> RegId {
+            [RegId::A, RegId::B, RegId::C, RegId::D][self.range(0..4) as usize]
+        }
+
+        fn param(&mut self, imm: std::ops::Range<i64>) -> Param {
+            if self.range(0..2) == 0 {
+                Param::Imm(self.range(imm))
+            } else {
+                Param::RegMode(self.reg())
+            }
+        }
+
+        fn regs(&mut self) -> Regs {
+            Regs {
+                a: self.range(0..6),
+                b: self.range(0..6),
+                c: self.range(0..6),
+                d: self.range(0..6),
+            }
+        }
+
+        /// Generate a program made up of multiplication loops, which may have been tampered
+        /// with, and random instructions
+        fn program(&mut self) -> Vec<Instruction> {
+            use Instruction as I;
+            let mut code = Vec::new();
+            while code.len() < 16 {
+                if self.range(0..3) == 0 {
+                    let [dest, counter, outer] = [self.reg(), self.reg(), self.reg()];
+                    // most loops need a positive count to terminate
+                    if self.range(0..5) != 0 {
+                        code.push(I::Cpy(Param::Imm(self.range(1..4)), Param::RegMode(outer)));
+                    }
+                    code.extend([
+                        I::Cpy(self.param(0..4), Param::RegMode(counter)),
+                        I::Inc(Param::RegMode(dest)),
+                        I::Dec(Param::RegMode(counter)),
+                        I::Jnz(Param::RegMode(counter), Param::Imm(-2)),
+                        I::Dec(Param::RegMode(outer)),
+                        I::Jnz(Param::RegMode(outer), Param::Imm(-5)),
+                    ]);
+                    if self.range(0..4) == 0 {
+                        let i = code.len() - 1 - self.range(0..6) as usize;
+                        code[i].toggle();
+                    }
+                } else {
+                    let x = Param::RegMode(self.reg());
+                    code.push(match self.range(0..5) {
+                        0 => I::Cpy(self.param(-2..5), x),
+                        1 => I::Inc(x),
+                        2 => I::Dec(x),
+                        3 => I::Jnz(self.param(0..2), self.param(-3..6)),
+                        _ => I::Tgl(self.param(-8..8)),
+                    });
+                }
+            }
+            code
+        }
+    }
+
+    /// Make sure that the optimizer never changes the result of a program
+    #[test]
+    fn fuzz_optimizer() {
+        let mut generator = ProgramGen(0x2016_0023);
+        let (mut compared, mut fused) = (0, 0);
+        for _ in 0..5000 {
+            let mut plain = Vm::new(generator.program());
+            plain.regs = generator.regs();
+            let mut optimized = plain.clone();
+            optimized.enable_optimizer();
+            // only compare programs that halt
+            let Some(plain_steps) = (0..5000).position(|_| plain.step() == Step::Halted) else {
+                continue;
+            };
+            let optimized_steps = (0..5000).position(|_| optimized.step() == Step::Halted);
+            assert_eq!(plain, optimized, "{:?}", plain.code());
+            compared += 1;
+            if optimized_steps < Some(plain_steps) {
+                fused += 1;
+            }
+        }
+        assert!(compared > 300, "only {compared} programs halted");
+        assert!(fused > 100, "only {fused} programs used the optimizer");
+    }
+}
@@ -0,0 +1,723 @@
+// SPDX-FileCopyrightText: 2025 Eli Array Minkoff
+//
+// SPDX-License-Identifier: 0BSD
+
+//! Module providing an ElfCode interpreter, as used in 2018 days 16, 19 and 21, which can be
+//! constructed with [`Vm::new`].
+//!
+//! Programs are usually written with mnemonics and an optional `#ip` directive, and parsed with
+//! [`parse`]. Day 16's programs are written with numeric opcodes instead, which can be worked out
+//! from samples of their behaviour with [`deduce_opcodes`], then decoded with [`decode`].
+//!
+//! # Instruction pointer binding
+//!
+//! With `#ip N`, register `N` is bound to the instruction pointer: it's written to the register
+//! before each instruction is executed, and read back afterwards before moving on to the next
+//! instruction, so writing to the register is a jump to the instruction after the value written.
+//! The program halts once the instruction pointer leaves the program. Arithmetic wraps around on
+//! overflow.
+//!
+//! # Hooks
+//!
+//! [`Vm::run_with`] calls a hook before each instruction, which can inspect the machine and stop
+//! it early. This is useful for programs that would otherwise run forever, or take far too long
+//! to run.
+//!
+//! For working out what such programs actually do, `tooling/elfcode-decompiler` turns them into
+//! structured pseudo-code.
+//!
+//! # Example
+//! ```rust
+//! use elfcode::Vm;
+//! use std::ops::ControlFlow;
+//! let program = elfcode::parse("#ip 1\nseti 5 0 2\naddi 0 1 0\ngtri 0 3 3\naddr 1 3 1\nseti 0 0 1")
+//!     .unwrap();
+//! let mut vm = Vm::new(program.clone());
+//! assert_eq!(vm.run()[0], 4);
+//! // stop once register 0 reaches 2
+//! let mut vm = Vm::new(program);
+//! assert_eq!(
+//!     vm.run_with(|vm, _| {
+//!         if vm.regs[0] == 2 {
+//!             ControlFlow::Break(vm.ip())
+//!         } else {
+//!             ControlFlow::Continue(())
+//!         }
+//!     }),
+//!     Some(2)
+//! );
+//! ```
+
+#![cfg_attr(
+    aoc_direct,
+    allow(dead_code, reason = "file written as standalone crate")
+)]
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::str::FromStr;
+
+/// The number of registers in the machine. Day 16's device only has 4, but that makes no
+/// difference to programs that only use those.
+pub const NUM_REGS: usize = 6;
+
+pub type Regs = [usize; NUM_REGS];
+
+/// A line of ElfCode, or a day 16 sample, that couldn't be parsed
+#[derive(Debug, PartialEq, Clone)]
+pub struct ElfCodeParseError {
+    /// The 1-indexed line number, or 0 if parsing a single instruction or sample
+    pub line: usize,
+    pub text: String,
+}
+
+impl fmt::Display for ElfCodeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: invalid instruction {:?}", self.line, self.text)
+    }
+}
+
+impl std::error::Error for ElfCodeParseError {}
+
+/// How an instruction treats one of its input operands
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Operand {
+    /// The operand is the number of a register to read
+    Reg,
+    /// The operand is used as-is
+    Imm,
+    /// The operand is ignored
+    Unused,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Opcode {
+    Addr,
+    Addi,
+    Mulr,
+    Muli,
+    Banr,
+    Bani,
+    Borr,
+    Bori,
+    Setr,
+    Seti,
+    Gtir,
+    Gtri,
+    Gtrr,
+    Eqir,
+    Eqri,
+    Eqrr,
+}
+
+/// The mnemonic for each opcode, and how it treats its `a` and `b` operands
+const OPCODE_TABLE: [(Opcode, &str, Operand, Operand); 16] = {
+    use Opcode as O;
+    use Operand::{Imm, Reg, Unused};
+    [
+        (O::Addr, "addr", Reg, Reg),
+        (O::Addi, "addi", Reg, Imm),
+        (O::Mulr, "mulr", Reg, Reg),
+        (O::Muli, "muli", Reg, Imm),
+        (O::Banr, "banr", Reg, Reg),
+        (O::Bani, "bani", Reg, Imm),
+        (O::Borr, "borr", Reg, Reg),
+        (O::Bori, "bori", Reg, Imm),
+        (O::Setr, "setr", Reg, Unused),
+        (O::Seti, "seti", Imm, Unused),
+        (O::Gtir, "gtir", Imm, Reg),
+        (O::Gtri, "gtri", Reg, Imm),
+        (O::Gtrr, "gtrr", Reg, Reg),
+        (O::Eqir, "eqir", Imm, Reg),
+        (O::Eqri, "eqri", Reg, Imm),
+        (O::Eqrr, "eqrr", Reg, Reg),
+    ]
+};
+
+impl Opcode {
+    /// Every opcode, in the order they're listed in the puzzle
+    pub const ALL: [Opcode; 16] = {
+        let mut all = [Opcode::Addr; 16];
+        let mut i = 0;
+        while i < 16 {
+            all[i] = OPCODE_TABLE[i].0;
+            i += 1;
+        }
+        all
+    };
+
+    fn table_entry(self) -> &'static (Opcode, &'static str, Operand, Operand) {
+        // the table is in the same order as the enum's variants
+        &OPCODE_TABLE[self as usize]
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        self.table_entry().1
+    }
+
+    /// How the opcode treats its `a` and `b` operands
+    pub fn operands(self) -> [Operand; 2] {
+        let &(_, _, a, b) = self.table_entry();
+        [a, b]
+    }
+
+    /// The value that an instruction with this opcode would write to its destination register,
+    /// or `None` if it would read a register outside of `regs`
+    pub fn eval(self, regs: &[usize], a: usize, b: usize) -> Option<usize> {
+        let read = |operand, kind| match kind {
+            Operand::Reg => regs.get(operand).copied(),
+            Operand::Imm | Operand::Unused => Some(operand),
+        };
+        let [a_kind, b_kind] = self.operands();
+        let (a, b) = (read(a, a_kind)?, read(b, b_kind)?);
+        Some(match self {
+            Opcode::Addr | Opcode::Addi => a.wrapping_add(b),
+            Opcode::Mulr | Opcode::Muli => a.wrapping_mul(b),
+            Opcode::Banr | Opcode::Bani => a & b,
+            Opcode::Borr | Opcode::Bori => a | b,
+            Opcode::Setr | Opcode::Seti => a,
+            Opcode::Gtir | Opcode::Gtri | Opcode::Gtrr => usize::from(a > b),
+            Opcode::Eqir | Opcode::Eqri | Opcode::Eqrr => usize::from(a == b),
+        })
+    }
+}
+
+impl FromStr for Opcode {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OPCODE_TABLE
+            .iter()
+            .find(|(_, mnemonic, ..)| *mnemonic == s)
+            .map(|&(opcode, ..)| opcode)
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.mnemonic())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub a: usize,
+    pub b: usize,
+    /// The register that the result is written to
+    pub c: usize,
+}
+
+impl Instruction {
+    /// Execute the instruction, returning `None` without changing anything if it uses a register
+    /// outside of `regs`
+    pub fn execute(&self, regs: &mut [usize]) -> Option<()> {
+        let value = self.opcode.eval(regs, self.a, self.b)?;
+        *regs.get_mut(self.c)? = value;
+        Some(())
+    }
+
+    /// Whether every register the instruction uses is within a machine with `num_regs` registers
+    pub fn is_valid(&self, num_regs: usize) -> bool {
+        let [a, b] = self.opcode.operands();
+        (a != Operand::Reg || self.a < num_regs)
+            && (b != Operand::Reg || self.b < num_regs)
+            && self.c < num_regs
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ElfCodeParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = s.split_whitespace().collect();
+        let instruction = match &words[..] {
+            [opcode, a, b, c] => (|| {
+                Some(Instruction {
+                    opcode: opcode.parse().ok()?,
+                    a: a.parse().ok()?,
+                    b: b.parse().ok()?,
+                    c: c.parse().ok()?,
+                })
+            })(),
+            _ => None,
+        };
+        instruction
+            .filter(|i| i.is_valid(NUM_REGS))
+            .ok_or_else(|| ElfCodeParseError {
+                line: 0,
+                text: s.to_string(),
+            })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.opcode, self.a, self.b, self.c)
+    }
+}
+
+/// A parsed program, along with the register bound to the instruction pointer, if any
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Program {
+    pub ip_binding: Option<usize>,
+    pub code: Vec<Instruction>,
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ip) = self.ip_binding {
+            writeln!(f, "#ip {ip}")?;
+        }
+        for instruction in &self.code {
+            writeln!(f, "{instruction}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a program, with one instruction per line, optionally preceded by an `#ip` directive
+pub fn parse(src: &str) -> Result<Program, ElfCodeParseError> {
+    let mut program = Program::default();
+    for (i, line) in src.lines().enumerate() {
+        let error = || ElfCodeParseError {
+            line: i + 1,
+            text: line.to_string(),
+        };
+        if let Some(ip) = line.strip_prefix("#ip ") {
+            // the directive has to come before any instructions
+            if i != 0 {
+                return Err(error());
+            }
+            let ip = ip.trim().parse().map_err(|_| error())?;
+            if ip >= NUM_REGS {
+                return Err(error());
+            }
+            program.ip_binding = Some(ip);
+        } else {
+            program.code.push(line.parse().map_err(|_| error())?);
+        }
+    }
+    Ok(program)
+}
+
+/// The opcode for each numeric opcode used by day 16's device
+pub type OpcodeMap = [Opcode; 16];
+
+/// Decode a program written with numeric opcodes, with one instruction per line
+pub fn decode(src: &str, opcodes: &OpcodeMap) -> Result<Vec<Instruction>, ElfCodeParseError> {
+    src.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let numbers: Vec<usize> = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .unwrap_or_default();
+            match numbers[..] {
+                [opcode, a, b, c] if opcode < 16 => Some(Instruction {
+                    opcode: opcodes[opcode],
+                    a,
+                    b,
+                    c,
+                }),
+                _ => None,
+            }
+            .filter(|i| i.is_valid(NUM_REGS))
+            .ok_or_else(|| ElfCodeParseError {
+                line: i + 1,
+                text: line.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// A sample of an unknown instruction's behaviour from day 16, in the form
+///
+/// ```text
+/// Before: [3, 2, 1, 1]
+/// 9 2 1 2
+/// After:  [3, 2, 2, 1]
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Sample {
+    pub before: [usize; 4],
+    /// The numeric opcode, followed by the `a`, `b` and `c` operands
+    pub instruction: [usize; 4],
+    pub after: [usize; 4],
+}
+
+impl Sample {
+    /// Every opcode that behaves as the sample shows
+    pub fn candidates(&self) -> impl Iterator<Item = Opcode> + '_ {
+        let [_, a, b, c] = self.instruction;
+        Opcode::ALL.into_iter().filter(move |&opcode| {
+            let mut regs = self.before;
+            Instruction { opcode, a, b, c }.execute(&mut regs).is_some() && regs == self.after
+        })
+    }
+}
+
+impl FromStr for Sample {
+    type Err = ElfCodeParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn numbers(s: &str, separator: &str) -> Option<[usize; 4]> {
+            s.split(separator)
+                .map(|n| n.trim().parse().ok())
+                .collect::<Option<Vec<_>>>()?
+                .try_into()
+                .ok()
+        }
+        let mut lines = s.lines();
+        (|| {
+            let before = lines.next()?.strip_prefix("Before: [")?.strip_suffix(']')?;
+            let instruction = numbers(lines.next()?, " ")?;
+            let after = lines.next()?.strip_prefix("After:  [")?.strip_suffix(']')?;
+            Some(Sample {
+                before: numbers(before, ",")?,
+                instruction,
+                after: numbers(after, ",")?,
+            })
+        })()
+        .filter(|_| lines.all(str::is_empty))
+        .ok_or_else(|| ElfCodeParseError {
+            line: 0,
+            text: s.to_string(),
+        })
+    }
+}
+
+/// A reason that [`deduce_opcodes`] couldn't work out which opcode is which
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DeductionError {
+    /// No opcode behaves as every sample with this numeric opcode shows
+    NoCandidates(usize),
+    /// The samples don't narrow down these numeric opcodes to a single opcode each
+    Ambiguous(Vec<usize>),
+    /// A sample's numeric opcode is 16 or more
+    InvalidOpcode(usize),
+}
+
+impl fmt::Display for DeductionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeductionError::NoCandidates(op) => write!(f, "no opcode matches every sample of {op}"),
+            DeductionError::Ambiguous(ops) => write!(f, "could not narrow down opcodes {ops:?}"),
+            DeductionError::InvalidOpcode(op) => write!(f, "{op} is not a valid opcode"),
+        }
+    }
+}
+
+impl std::error::Error for DeductionError {}
+
+/// Work out which opcode each numeric opcode refers to, based on samples of their behaviour
+///
+/// First, each numeric opcode is narrowed down to the opcodes that match every one of its
+/// samples. Then, whenever a numeric opcode only has one candidate left, that opcode is ruled out
+/// for every other numeric opcode, until nothing else can be ruled out.
+pub fn deduce_opcodes<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+) -> Result<OpcodeMap, DeductionError> {
+    let mut possibilities: [BTreeSet<usize>; 16] = core::array::from_fn(|_| (0..16).collect());
+    for sample in samples {
+        let op = sample.instruction[0];
+        let candidates: BTreeSet<usize> =
+            sample.candidates().map(|opcode| opcode as usize).collect();
+        let set = possibilities
+            .get_mut(op)
+            .ok_or(DeductionError::InvalidOpcode(op))?;
+        set.retain(|opcode| candidates.contains(opcode));
+        if set.is_empty() {
+            return Err(DeductionError::NoCandidates(op));
+        }
+    }
+
+    let mut resolved = [false; 16];
+    while let Some(op) = (0..16).find(|&op| !resolved[op] && possibilities[op].len() == 1) {
+        resolved[op] = true;
+        let opcode = *possibilities[op].first().unwrap_or_else(|| unreachable!());
+        for (other, set) in possibilities.iter_mut().enumerate() {
+            if other != op {
+                set.remove(&opcode);
+                if set.is_empty() {
+                    return Err(DeductionError::NoCandidates(other));
+                }
+            }
+        }
+    }
+
+    let unresolved: Vec<usize> = (0..16).filter(|&op| !resolved[op]).collect();
+    if !unresolved.is_empty() {
+        return Err(DeductionError::Ambiguous(unresolved));
+    }
+    Ok(core::array::from_fn(|op| {
+        let opcode = *possibilities[op].first().unwrap_or_else(|| unreachable!());
+        Opcode::ALL[opcode]
+    }))
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Vm {
+    pub regs: Regs,
+    ip_binding: Option<usize>,
+    ip: usize,
+    code: Vec<Instruction>,
+}
+
+impl Vm {
+    /// # Panics
+    ///
+    /// Panics if the program uses a register that doesn't exist, which can't happen with a
+    /// program returned by [`parse`] or [`decode`]
+    pub fn new(program: Program) -> Self {
+        let Program { ip_binding, code } = program;
+        if let Some(bad) = code.iter().find(|i| !i.is_valid(NUM_REGS)) {
+            panic!("{bad} uses a register that doesn't exist");
+        }
+        if let Some(ip) = ip_binding {
+            assert!(
+                ip < NUM_REGS,
+                "#ip {ip} binds a register that doesn't exist"
+            );
+        }
+        Self {
+            regs: [0; NUM_REGS],
+            ip_binding,
+            ip: 0,
+            code,
+        }
+    }
+
+    /// The index of the next instruction to execute
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn ip_binding(&self) -> Option<usize> {
+        self.ip_binding
+    }
+
+    pub fn code(&self) -> &[Instruction] {
+        &self.code
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.ip >= self.code.len()
+    }
+
+    /// Get the next instruction, writing the instruction pointer to its register
+    fn fetch(&mut self) -> Option<Instruction> {
+        let instruction = *self.code.get(self.ip)?;
+        if let Some(ip_reg) = self.ip_binding {
+            self.regs[ip_reg] = self.ip;
+        }
+        Some(instruction)
+    }
+
+    fn execute(&mut self, instruction: Instruction) {
+        instruction
+            .execute(&mut self.regs)
+            .unwrap_or_else(|| unreachable!("registers are checked in Vm::new"));
+        if let Some(ip_reg) = self.ip_binding {
+            self.ip = self.regs[ip_reg];
+        }
+        // a jump to before the start of the program would need the instruction pointer to be
+        // negative, which a register can't hold
+        self.ip = self.ip.saturating_add(1);
+    }
+
+    /// Execute a single instruction, returning `false` if the program has already halted
+    pub fn step(&mut self) -> bool {
+        let Some(instruction) = self.fetch() else {
+            return false;
+        };
+        self.execute(instruction);
+        true
+    }
+
+    /// Run until the program halts, and return the final registers
+    pub fn run(&mut self) -> &Regs {
+        while self.step() {}
+        &self.regs
+    }
+
+    /// Run until the program halts, calling `hook` before each instruction with the machine, as
+    /// it is just before the instruction is executed, and the instruction itself.
+    ///
+    /// If `hook` breaks, the instruction isn't executed, and the value it broke with is returned.
+    /// Otherwise, `None` is returned once the program halts.
+    pub fn run_with<B>(
+        &mut self,
+        mut hook: impl FnMut(&Self, &Instruction) -> ControlFlow<B>,
+    ) -> Option<B> {
+        while let Some(instruction) = self.fetch() {
+            if let ControlFlow::Break(value) = hook(self, &instruction) {
+                return Some(value);
+            }
+            self.execute(instruction);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY19_EXAMPLE: &str = "#ip 0\nseti 5 0 1\nseti 6 0 2\naddi 0 1 0\naddr 1 2 3\nsetr 1 0 0\n\
+                                 seti 8 0 4\nseti 9 0 5";
+
+    #[test]
+    fn day19_example() {
+        let program = parse(DAY19_EXAMPLE).unwrap();
+        assert_eq!(program.ip_binding, Some(0));
+        let mut vm = Vm::new(program);
+        assert_eq!(vm.run(), &[6, 5, 6, 0, 0, 9]);
+        assert!(vm.is_halted());
+    }
+
+    #[test]
+    fn every_opcode() {
+        let regs = [3, 5, 9, 0];
+        let expected = [
+            (Opcode::Addr, 8),
+            (Opcode::Addi, 4),
+            (Opcode::Mulr, 15),
+            (Opcode::Muli, 3),
+            (Opcode::Banr, 1),
+            (Opcode::Bani, 1),
+            (Opcode::Borr, 7),
+            (Opcode::Bori, 3),
+            (Opcode::Setr, 3),
+            (Opcode::Seti, 0),
+            (Opcode::Gtir, 0),
+            (Opcode::Gtri, 1),
+            (Opcode::Gtrr, 0),
+            (Opcode::Eqir, 0),
+            (Opcode::Eqri, 0),
+            (Opcode::Eqrr, 0),
+        ];
+        assert_eq!(expected.map(|(opcode, _)| opcode), Opcode::ALL);
+        for (opcode, value) in expected {
+            assert_eq!(opcode.eval(&regs, 0, 1), Some(value), "{opcode}");
+        }
+        assert_eq!(Opcode::Addr.eval(&regs, 0, 4), None);
+        assert_eq!(Opcode::Addi.eval(&regs, 0, 4), Some(7));
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = parse(DAY19_EXAMPLE).unwrap();
+        assert_eq!(program.to_string(), format!("{DAY19_EXAMPLE}\n"));
+        for opcode in Opcode::ALL {
+            assert_eq!(opcode.mnemonic().parse(), Ok(opcode));
+        }
+    }
+
+    #[test]
+    fn invalid_instructions() {
+        assert_eq!(
+            parse("seti 5 0 1\naddr 1 6 0"),
+            Err(ElfCodeParseError {
+                line: 2,
+                text: "addr 1 6 0".into()
+            })
+        );
+        // immediate operands can be as large as needed
+        assert!(parse("addi 1 60 0").is_ok());
+        assert!(parse("seti 5 0 6").is_err());
+        assert!(parse("seti 5 0").is_err());
+        assert!(parse("nope 5 0 1").is_err());
+        assert!(parse("#ip 6").is_err());
+        assert!(parse("seti 5 0 1\n#ip 0").is_err());
+        assert!(decode("16 0 0 0", &Opcode::ALL).is_err());
+    }
+
+    #[test]
+    fn hooks() {
+        let mut vm = Vm::new(parse(DAY19_EXAMPLE).unwrap());
+        let mut trace = Vec::new();
+        let result: Option<()> = vm.run_with(|vm, instruction| {
+            // the instruction pointer is already in its register when the hook sees it
+            assert_eq!(vm.regs[0], vm.ip());
+            assert_eq!(vm.code()[vm.ip()], *instruction);
+            trace.push(vm.ip());
+            ControlFlow::Continue(())
+        });
+        assert_eq!(result, None);
+        assert_eq!(trace, [0, 1, 2, 4, 6]);
+
+        let mut vm = Vm::new(parse(DAY19_EXAMPLE).unwrap());
+        let result = vm.run_with(|vm, instruction| match instruction.opcode {
+            Opcode::Setr => ControlFlow::Break(vm.regs),
+            _ => ControlFlow::Continue(()),
+        });
+        assert_eq!(result, Some([4, 5, 6, 0, 0, 0]));
+        // the instruction that the hook broke on hasn't been executed
+        assert_eq!(vm.ip(), 4);
+        assert!(vm.step());
+        assert_eq!(vm.ip(), 6);
+        assert_eq!(vm.regs, [5, 5, 6, 0, 0, 0]);
+    }
+
+    #[test]
+    fn day16_example() {
+        let sample: Sample = "Before: [3, 2, 1, 1]\n9 2 1 2\nAfter:  [3, 2, 2, 1]"
+            .parse()
+            .unwrap();
+        assert_eq!(sample.instruction, [9, 2, 1, 2]);
+        assert_eq!(
+            sample.candidates().collect::<Vec<_>>(),
+            [Opcode::Addi, Opcode::Mulr, Opcode::Seti]
+        );
+        assert!(
+            "Before: [3, 2, 1]\n9 2 1 2\nAfter:  [3, 2, 2, 1]"
+                .parse::<Sample>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn deduction() {
+        // scramble the opcodes, as 5 and 16 are coprime
+        let secret: OpcodeMap = std::array::from_fn(|i| Opcode::ALL[(i * 5 + 3) % 16]);
+        // every instruction with every combination of registers, on a couple of machine states
+        let mut samples = Vec::new();
+        for (op, &opcode) in secret.iter().enumerate() {
+            for operands in 0..64 {
+                let instruction = [op, operands & 3, (operands >> 2) & 3, operands >> 4];
+                for before in [[1, 2, 3, 0], [3, 3, 0, 2]] {
+                    let mut after = before;
+                    let [_, a, b, c] = instruction;
+                    Instruction { opcode, a, b, c }.execute(&mut after).unwrap();
+                    samples.push(Sample {
+                        before,
+                        instruction,
+                        after,
+                    });
+                }
+            }
+        }
+        assert_eq!(deduce_opcodes(&samples), Ok(secret));
+
+        let decoded = decode("5 1 2 3\n0 0 0 0", &secret).unwrap();
+        assert_eq!(decoded[0].opcode, secret[5]);
+        assert_eq!(decoded[1].opcode, secret[0]);
+
+        // a couple of samples aren't enough to tell everything apart
+        assert!(matches!(
+            deduce_opcodes(&samples[..2]),
+            Err(DeductionError::Ambiguous(_))
+        ));
+        // addr can't turn 1 and 2 into 4
+        let impossible = Sample {
+            before: [1, 2, 0, 0],
+            instruction: [0, 0, 1, 2],
+            after: [1, 2, 4, 0],
+        };
+        assert_eq!(
+            deduce_opcodes(&[impossible]),
+            Err(DeductionError::NoCandidates(0))
+        );
+    }
+}
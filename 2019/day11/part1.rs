@@ -6,72 +6,37 @@
 
 // This is sounding an awful lot like Langton's Ant to me
 
-use std::collections::HashMap;
-
 // Moved the intcode interpreter code into its own module that can be copied over for future days
 pub mod intcode;
+use intcode::adapters::Grid;
+use intcode::robot::{self, Direction, Location, Turn, Turtle};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum PanelColor {
-    Black { repainted: bool },
+    Black,
     White,
 }
 
-impl PanelColor {
-    fn report(self) -> i64 {
-        i64::from(self == Self::White)
-    }
-
-    fn paint(&mut self, color: i64) {
-        *self = if color == 1 {
-            Self::White
-        } else {
-            assert_eq!(color, 0, "invalid paint color");
-            Self::Black { repainted: true }
-        };
-    }
-}
-
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Direction {
-    Up,
-    Right,
-    Down,
-    Left,
-}
+/// The painting robot's camera readings and paint and turn instructions
+struct Painter;
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
-struct Location {
-    x: i32,
-    y: i32,
-}
+impl Turtle for Painter {
+    type Tile = PanelColor;
 
-impl Direction {
-    fn rotate_left(&mut self) {
-        *self = match self {
-            Self::Up => Self::Right,
-            Self::Right => Self::Down,
-            Self::Down => Self::Left,
-            Self::Left => Self::Up,
-        }
+    fn sense(&self, panel: Option<&PanelColor>) -> i64 {
+        i64::from(panel == Some(&PanelColor::White))
     }
-    fn rotate_right(&mut self) {
-        *self = match self {
-            Self::Up => Self::Left,
-            Self::Right => Self::Up,
-            Self::Down => Self::Right,
-            Self::Left => Self::Down,
-        }
-    }
-}
 
-impl std::ops::AddAssign<Direction> for Location {
-    fn add_assign(&mut self, dir: Direction) {
-        match dir {
-            Direction::Up => self.y -= 1,
-            Direction::Right => self.x += 1,
-            Direction::Down => self.y += 1,
-            Direction::Left => self.x -= 1,
+    fn act(&self, [color, turn]: [i64; 2]) -> Option<(PanelColor, Turn)> {
+        let color = match color {
+            0 => PanelColor::Black,
+            1 => PanelColor::White,
+            _ => return None,
+        };
+        match turn {
+            0 => Some((color, Turn::Left)),
+            1 => Some((color, Turn::Right)),
+            _ => None,
         }
     }
 }
@@ -91,35 +56,17 @@ fn main() {
         interpreter.enable_logging();
     }
 
-    let mut panels: HashMap<Location, PanelColor> = HashMap::new();
-    let mut location = Location::default();
-    let mut direction = Direction::Up;
-
-    while let (outputs, intcode::State::Awaiting) = interpreter
-        .run_through_inputs(vec![panels.entry(location).or_default().report()])
-        .unwrap()
-    {
-        debug_assert_eq!(outputs.len(), 2);
-        panels.entry(location).or_default().paint(outputs[0]);
-        match outputs[1] {
-            0 => direction.rotate_right(),
-            1 => direction.rotate_left(),
-            i => panic!("invalid direction code: {i}"),
-        }
-        location += direction;
-    }
-
-    println!(
-        "{}",
-        panels
-            .into_values()
-            .filter(|v| *v != PanelColor::default())
-            .count()
-    );
-}
-
-impl Default for PanelColor {
-    fn default() -> Self {
-        Self::Black { repainted: false }
-    }
+    // every panel on the map has been painted at least once, as the robot only adds a panel when
+    // it paints it
+    let mut panels = Grid::default();
+    robot::drive(
+        &mut interpreter,
+        &Painter,
+        &mut panels,
+        Location::default(),
+        Direction::Up,
+    )
+    .unwrap();
+
+    println!("{}", panels.len());
 }
@@ -6,56 +6,9 @@
 
 mod intcode;
 use intcode::Interpreter;
-use std::collections::{BTreeMap, BinaryHeap, HashMap};
-
-#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
-struct Location {
-    y: i16,
-    x: i16,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Direction {
-    North = 1,
-    South = 2,
-    West = 3,
-    East = 4,
-}
-
-impl Location {
-    fn neighbors(self) -> [(Self, Direction); 4] {
-        [
-            (
-                Self {
-                    y: self.y - 1,
-                    ..self
-                },
-                Direction::North,
-            ),
-            (
-                Self {
-                    y: self.y + 1,
-                    ..self
-                },
-                Direction::South,
-            ),
-            (
-                Self {
-                    x: self.x - 1,
-                    ..self
-                },
-                Direction::West,
-            ),
-            (
-                Self {
-                    x: self.x + 1,
-                    ..self
-                },
-                Direction::East,
-            ),
-        ]
-    }
-}
+use intcode::adapters::Grid;
+use intcode::robot::{self, Direction, Location, Protocol, Search};
+use std::collections::{BinaryHeap, HashMap};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum RoomType {
@@ -64,41 +17,43 @@ enum RoomType {
     Oxy,
 }
 
-fn build_map(interpreter: Interpreter) -> HashMap<Location, RoomType> {
-    let mut rooms: HashMap<Location, RoomType> =
-        HashMap::from([(Location { x: 0, y: 0 }, RoomType::Open)]);
-    let mut to_process: BTreeMap<Location, Vec<Direction>> =
-        BTreeMap::from([(Location { x: 0, y: 0 }, Vec::new())]);
+/// The repair droid's movement commands and status codes
+struct RepairDroid;
 
-    while let Some((room, path)) = to_process.pop_first() {
-        let (output, state) = interpreter
-            .clone()
-            .run_through_inputs(path.iter().map(|d| *d as i64))
-            .unwrap();
-        debug_assert!(output.iter().all(|i| matches!(*i, 0..=2)));
+impl Protocol for RepairDroid {
+    type Tile = RoomType;
 
-        let room_type = match output.last().cloned().unwrap_or(1) {
-            0 => RoomType::Wall,
-            1 => RoomType::Open,
-            2 => RoomType::Oxy,
-            i => panic!("Invalid status code {i} reported"),
-        };
-        rooms.insert(room, room_type);
-        if room_type == RoomType::Wall || state == intcode::State::Halted {
-            continue;
+    fn command(&self, dir: Direction) -> i64 {
+        match dir {
+            Direction::Up => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+            Direction::Right => 4,
         }
-        for (loc, dir) in room.neighbors() {
-            if !rooms.contains_key(&loc) {
-                to_process.entry(loc).or_insert({
-                    let mut v = path.clone();
-                    v.push(dir);
-                    v
-                });
-            }
+    }
+
+    fn status(&self, value: i64) -> Option<RoomType> {
+        match value {
+            0 => Some(RoomType::Wall),
+            1 => Some(RoomType::Open),
+            2 => Some(RoomType::Oxy),
+            _ => None,
         }
     }
 
-    rooms
+    fn is_passable(&self, tile: RoomType) -> bool {
+        tile != RoomType::Wall
+    }
+}
+
+fn build_map(interpreter: Interpreter) -> Grid<RoomType> {
+    robot::explore(
+        &interpreter,
+        &RepairDroid,
+        RoomType::Open,
+        Search::BreadthFirst,
+    )
+    .unwrap()
 }
 
 fn main() {
@@ -112,19 +67,22 @@ fn main() {
 
 /// Implementation of Dijkstra's algorithm to find the length of the shortest path from 0, 0 to the
 /// oxygen
-fn find_max_distance(map: HashMap<Location, RoomType>) -> u32 {
+fn find_max_distance(map: Grid<RoomType>) -> u32 {
     use std::cmp::Reverse as Rev;
 
     let mut distances: HashMap<Location, u32> = HashMap::with_capacity(map.len());
     let mut queue: BinaryHeap<Rev<(u32, Location)>> = BinaryHeap::with_capacity(map.len());
-    queue.push(Rev((0, Location { x: 0, y: 0 })));
+    queue.push(Rev((0, Location::default())));
     while let Some(Rev((dist, loc))) = queue.pop() {
         if distances.get(&loc).is_some_and(|prev| *prev < dist) {
             continue;
         }
 
         for (neighbor, _) in loc.neighbors().into_iter() {
-            if map[&neighbor] == RoomType::Wall {
+            if neighbor
+                .get(&map)
+                .is_none_or(|&room| room == RoomType::Wall)
+            {
                 continue;
             }
             let new_dist = dist + 1;
@@ -135,9 +93,9 @@ fn find_max_distance(map: HashMap<Location, RoomType>) -> u32 {
         }
     }
 
-    map.into_iter()
-        .filter(|(_, r)| *r == RoomType::Oxy)
-        .map(|(l, _)| distances[&l])
+    map.iter()
+        .filter(|(_, r)| **r == RoomType::Oxy)
+        .map(|((x, y), _)| distances[&Location::new(x, y)])
         .min()
         .unwrap()
 }
@@ -21,59 +21,9 @@
 mod intcode;
 
 use intcode::Interpreter;
+use intcode::robot::{Direction, Location};
 use std::collections::BTreeSet;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-struct Location {
-    y: usize,
-    x: usize,
-}
-
-impl Location {
-    fn next_in(self, direction: Direction) -> Self {
-        match direction {
-            Direction::Up => Self {
-                y: self.y - 1,
-                ..self
-            },
-            Direction::Right => Self {
-                x: self.x + 1,
-                ..self
-            },
-            Direction::Down => Self {
-                y: self.y + 1,
-                ..self
-            },
-            Direction::Left => Self {
-                x: self.x - 1,
-                ..self
-            },
-        }
-    }
-}
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum Direction {
-    Up,
-    Left,
-    Down,
-    Right,
-}
-
-impl Direction {
-    const fn rotate_right(self) -> Self {
-        match self {
-            Self::Up => Self::Right,
-            Self::Right => Self::Down,
-            Self::Down => Self::Left,
-            Self::Left => Self::Up,
-        }
-    }
-    const fn rotate_left(self) -> Self {
-        self.rotate_right().rotate_right().rotate_right()
-    }
-}
-
 fn parse_start(mut interpreter: Interpreter) -> (BTreeSet<Location>, Location, Direction) {
     let (output, intcode::State::Halted) =
         interpreter.run_through_inputs(std::iter::empty()).unwrap()
@@ -83,14 +33,13 @@ fn parse_start(mut interpreter: Interpreter) -> (BTreeSet<Location>, Location, D
     let mut map = BTreeSet::new();
     let mut bot: Option<(Location, Direction)> = None;
 
-    // start at 1 to simplify the logic to build the directions
-    let mut x = 1;
-    let mut y = 1;
+    let mut x = 0;
+    let mut y = 0;
     for c in output {
         match u8::try_from(c).unwrap() {
             b'.' => x += 1,
             b'\n' => {
-                x = 1;
+                x = 0;
                 y += 1
             }
 
@@ -98,32 +47,15 @@ fn parse_start(mut interpreter: Interpreter) -> (BTreeSet<Location>, Location, D
                 x += 1;
                 map.insert(Location { x, y });
             }
-            b'v' => {
-                assert!(bot.is_none());
-                x += 1;
-                map.insert(Location { x, y });
-                bot = Some((Location { x, y }, Direction::Down));
-            }
-            b'<' => {
-                assert!(bot.is_none());
-                x += 1;
-                map.insert(Location { x, y });
-                bot = Some((Location { x, y }, Direction::Left));
-            }
-            b'^' => {
-                assert!(bot.is_none());
-                x += 1;
-                map.insert(Location { x, y });
-                bot = Some((Location { x, y }, Direction::Up));
-            }
-            b'>' => {
+            b'X' => panic!("bot not on scaffolding"),
+            b => {
+                let dir = Direction::from_arrow(b)
+                    .unwrap_or_else(|| panic!("Unknown output {}", b.escape_ascii()));
                 assert!(bot.is_none());
                 x += 1;
                 map.insert(Location { x, y });
-                bot = Some((Location { x, y }, Direction::Right));
+                bot = Some((Location { x, y }, dir));
             }
-            b'X' => panic!("bot not on scaffolding"),
-            b => panic!("Unknown output {}", b.escape_ascii()),
         }
     }
 
@@ -142,14 +74,14 @@ fn build_instructions(
     let mut move_len: u32 = 0;
     while !grid.is_empty() {
         // remove the current location unless it's an intersectio that'll need to be crossed again
-        if !grid.contains(&bot_loc.next_in(bot_dir.rotate_right()))
-            && !grid.contains(&bot_loc.next_in(bot_dir.rotate_left()))
+        if !grid.contains(&bot_loc.step(bot_dir.turn_right()))
+            && !grid.contains(&bot_loc.step(bot_dir.turn_left()))
         {
             grid.remove(&bot_loc);
         }
-        if grid.contains(&bot_loc.next_in(bot_dir)) {
+        if grid.contains(&bot_loc.step(bot_dir)) {
             move_len += 1;
-            bot_loc = bot_loc.next_in(bot_dir);
+            bot_loc = bot_loc.step(bot_dir);
             continue;
         }
         if move_len > 0 {
@@ -157,12 +89,12 @@ fn build_instructions(
             move_len = 0;
         }
 
-        if grid.contains(&bot_loc.next_in(bot_dir.rotate_right())) {
+        if grid.contains(&bot_loc.step(bot_dir.turn_right())) {
             instructions.push_str("R,");
-            bot_dir = bot_dir.rotate_right()
-        } else if grid.contains(&bot_loc.next_in(bot_dir.rotate_left())) {
+            bot_dir = bot_dir.turn_right()
+        } else if grid.contains(&bot_loc.step(bot_dir.turn_left())) {
             instructions.push_str("L,");
-            bot_dir = bot_dir.rotate_left()
+            bot_dir = bot_dir.turn_left()
         } else {
             assert!(grid.is_empty(), "solution assumes linear path");
         }
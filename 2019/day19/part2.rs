@@ -43,13 +43,13 @@ impl IntoIterator for Span {
 }
 
 struct CachingRunner {
-    interpreter: Interpreter,
+    interpreter: Interpreter<'static>,
     row_spans: HashMap<i64, Span>,
-    interpreter_cache: HashMap<i64, Interpreter>,
+    interpreter_cache: HashMap<i64, Interpreter<'static>>,
 }
 
 impl CachingRunner {
-    fn new(interpreter: Interpreter) -> Self {
+    fn new(interpreter: Interpreter<'static>) -> Self {
         Self {
             interpreter,
             row_spans: HashMap::new(),
@@ -57,7 +57,7 @@ impl CachingRunner {
         }
     }
 
-    fn get_interpreter(&mut self, x: i64) -> Interpreter {
+    fn get_interpreter(&mut self, x: i64) -> Interpreter<'static> {
         self.interpreter_cache
             .entry(x)
             .or_insert_with(|| {
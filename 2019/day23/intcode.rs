@@ -25,6 +25,11 @@ use std::num::TryFromIntError;
 
 /// A sort of logical memory management unit, using a hashmap to split memory into segments, which
 /// are each contiguous in memory.
+///
+/// A page is allocated the first time it's written to, but only pages containing at least one
+/// non-zero value are considered populated. Pages that aren't populated are indistinguishable from
+/// unallocated ones, so they're ignored when comparing, debug-printing or iterating, but they still
+/// count towards the number of pages in use.
 mod mmu {
     use std::collections::{BTreeMap, BTreeSet, HashMap};
     use std::fmt;
@@ -34,81 +39,132 @@ mod mmu {
         assert!(std::mem::size_of::<[i64; 512]>() == 4096);
     };
 
+    #[derive(Clone)]
     pub(super) struct IntcodeMem {
         segments: HashMap<u64, [i64; 512]>,
+        /// Pages written to since dirty tracking was last reset
+        dirty: BTreeSet<u64>,
+    }
+
+    fn is_populated(page: &[i64; 512]) -> bool {
+        page.iter().any(|&i| i != 0)
     }
 
     impl IntcodeMem {
         pub(super) fn get(&self, i: u64) -> i64 {
-            if self.segments.contains_key(&(i & !511)) {
-                self[i]
-            } else {
-                0
+            self[i]
+        }
+        /// The number of allocated pages, whether populated or not
+        pub(super) fn page_count(&self) -> usize {
+            self.segments.len()
+        }
+        /// Whether the page containing `i` has been allocated
+        pub(super) fn is_mapped(&self, i: u64) -> bool {
+            self.segments.contains_key(&(i & !511))
+        }
+        /// One past the highest address holding a non-zero value
+        pub(super) fn extent(&self) -> u64 {
+            self.segments
+                .iter()
+                .filter_map(|(&k, v)| v.iter().rposition(|&i| i != 0).map(|i| k + i as u64 + 1))
+                .max()
+                .unwrap_or_default()
+        }
+        /// Create memory from allocated pages, with nothing marked as dirty
+        pub(super) fn from_pages(pages: impl IntoIterator<Item = (u64, [i64; 512])>) -> Self {
+            Self {
+                segments: pages.into_iter().collect(),
+                dirty: BTreeSet::new(),
+            }
+        }
+        /// Iterate over the allocated pages, whether populated or not, in address order
+        pub(super) fn pages(&self) -> impl Iterator<Item = (u64, &[i64; 512])> {
+            self.segments
+                .iter()
+                .map(|(&k, v)| (k, v))
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+        }
+        /// The address ranges of runs of consecutive populated pages, each ending after its last
+        /// non-zero value, in address order
+        pub(super) fn populated_ranges(&self) -> Vec<(u64, u64)> {
+            let mut ranges: Vec<(u64, u64)> = Vec::new();
+            let mut last_page = None;
+            for (addr, page) in self.populated_pages() {
+                let end = addr
+                    + page
+                        .iter()
+                        .rposition(|&i| i != 0)
+                        .map_or(0, |i| i as u64 + 1);
+                match ranges.last_mut() {
+                    Some((_, run_end)) if last_page == addr.checked_sub(512) => *run_end = end,
+                    _ => ranges.push((addr, end)),
+                }
+                last_page = Some(addr);
             }
+            ranges
         }
-        fn active_segments(&self) -> BTreeSet<u64> {
+        /// Iterate over the populated pages, in address order
+        pub(super) fn populated_pages(&self) -> impl Iterator<Item = (u64, &[i64; 512])> {
             self.segments
                 .iter()
-                .filter_map(|(&k, &v)| if v == [0; 512] { Some(k) } else { None })
-                .collect()
+                .filter(|(_, page)| is_populated(page))
+                .map(|(&k, v)| (k, v))
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+        }
+        /// Iterate over the starting addresses of pages written to since dirty tracking was last
+        /// reset, in address order
+        pub(super) fn dirty_pages(&self) -> impl Iterator<Item = u64> {
+            self.dirty.iter().copied()
+        }
+        pub(super) fn clear_dirty(&mut self) {
+            self.dirty.clear();
         }
     }
 
     impl PartialEq for IntcodeMem {
         fn eq(&self, other: &Self) -> bool {
-            let active_segments = self.active_segments();
-            other.active_segments() == active_segments
-                && active_segments
-                    .into_iter()
-                    .all(|seg| self.segments[&seg] == other.segments[&seg])
+            self.populated_pages().eq(other.populated_pages())
         }
     }
 
     impl std::iter::FromIterator<i64> for IntcodeMem {
         fn from_iter<I: IntoIterator<Item = i64>>(iter: I) -> Self {
             let holder: Vec<i64> = iter.into_iter().collect();
-            let mut page_number = 0;
             let mut segments = HashMap::with_capacity(holder.len().div_ceil(512));
-            let (full_segments, partial_segment) = holder.as_chunks();
-            for segment in full_segments {
-                segments.insert(page_number, *segment);
-                page_number += 512;
+            for (page_number, chunk) in (0..).step_by(512).zip(holder.chunks(512)) {
+                let mut segment = [0; 512];
+                segment[..chunk.len()].copy_from_slice(chunk);
+                if is_populated(&segment) {
+                    segments.insert(page_number, segment);
+                }
             }
-            let mut final_segment = [0; 512];
-            final_segment[..partial_segment.len()].clone_from_slice(partial_segment);
-            segments.insert(page_number, final_segment);
 
-            Self { segments }
+            Self {
+                segments,
+                dirty: BTreeSet::new(),
+            }
         }
     }
 
     impl std::ops::Index<u64> for IntcodeMem {
         type Output = i64;
         fn index(&self, i: u64) -> &i64 {
-            &self.segments[&(i & !511)][i as usize & 511]
+            self.segments
+                .get(&(i & !511))
+                .map_or(&0, |segment| &segment[i as usize & 511])
         }
     }
 
     impl std::ops::IndexMut<u64> for IntcodeMem {
         fn index_mut(&mut self, i: u64) -> &mut i64 {
             let segment_index = i as usize & 511;
+            self.dirty.insert(i & !511);
             &mut self.segments.entry(i & !511).or_insert_with(|| [0; 512])[segment_index]
         }
     }
 
-    impl Clone for IntcodeMem {
-        fn clone(&self) -> Self {
-            // don't copy blank pages
-            let segments = self
-                .segments
-                .iter()
-                .filter(|(_, mem)| mem[..] != [0_i64; 512])
-                .map(|(k, v)| (*k, *v))
-                .collect();
-            Self { segments }
-        }
-    }
-
     pub(super) struct IntcodeMemIter {
         segments: BTreeMap<u64, [i64; 512]>,
         current_segment: u64,
@@ -118,15 +174,14 @@ mod mmu {
     impl Iterator for IntcodeMemIter {
         type Item = i64;
         fn next(&mut self) -> Option<i64> {
-            if self.current_segment > self.segments.keys().max().cloned().unwrap_or_default() {
+            let &last_segment = self.segments.keys().next_back()?;
+            if self.current_segment > last_segment {
                 return None;
             }
-            let ret: i64;
-            if let Some(segment) = self.segments.get(&self.current_segment) {
-                ret = segment[self.segment_index];
-            } else {
-                ret = 0;
-            }
+            let ret = self
+                .segments
+                .get(&self.current_segment)
+                .map_or(0, |segment| segment[self.segment_index]);
 
             self.segment_index += 1;
             if self.segment_index == 512 {
@@ -141,9 +196,14 @@ mod mmu {
     impl IntoIterator for IntcodeMem {
         type Item = i64;
         type IntoIter = IntcodeMemIter;
+        /// Iterate over every value from address 0 through the end of the last populated page
         fn into_iter(self) -> IntcodeMemIter {
             IntcodeMemIter {
-                segments: self.segments.into_iter().collect(),
+                segments: self
+                    .segments
+                    .into_iter()
+                    .filter(|(_, page)| is_populated(page))
+                    .collect(),
                 current_segment: 0,
                 segment_index: 0,
             }
@@ -153,11 +213,10 @@ mod mmu {
     impl fmt::Debug for IntcodeMem {
         fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
             let mut fmtstruct = fmt.debug_struct("IntcodeMem");
-            let segment_nums: BTreeSet<_> = self.segments.keys().collect();
-            for sn in segment_nums {
+            for (sn, segment) in self.populated_pages() {
                 fmtstruct.field(
                     &format!("{{ segment 0x{sn:04x} }}"),
-                    &format_args!("{:?}", self.segments[sn]),
+                    &format_args!("{segment:?}"),
                 );
             }
 
@@ -167,12 +226,53 @@ mod mmu {
 }
 
 use mmu::IntcodeMem;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::io;
+use std::rc::Rc;
 
 #[derive(Debug, PartialEq)]
 pub enum State {
     Awaiting,
     Halted,
+    /// Execution was paused by a breakpoint or watchpoint
+    Break(Breakpoint),
+}
+
+/// The breakpoint or watchpoint that caused execution to pause
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Breakpoint {
+    /// The instruction at the address is about to be executed
+    Address(u64),
+    /// The instruction at `ip` read the watched address `addr`
+    Read { ip: u64, addr: u64 },
+    /// The instruction at `ip` wrote `new` to the watched address `addr`, which held `old`
+    Write {
+        ip: u64,
+        addr: u64,
+        old: i64,
+        new: i64,
+    },
+}
+
+/// The kinds of memory access that a watchpoint triggers on
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Watch {
+    Read,
+    Write,
+    Access,
+}
+
+/// Breakpoints and watchpoints set on an interpreter, along with the bookkeeping needed to resume
+/// after one is hit
+#[derive(Debug, Default, Clone)]
+struct Debugger {
+    breakpoints: BTreeSet<u64>,
+    watchpoints: BTreeMap<u64, Watch>,
+    /// Address of a breakpoint that was just hit, which should not fire again until the
+    /// instruction there has been executed
+    resume_from: Option<u64>,
+    /// The first watchpoint hit by the instruction currently being executed
+    watch_hit: Option<Breakpoint>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -182,15 +282,117 @@ pub enum ErrorState {
     NegativeMemAccess(TryFromIntError),
     WriteToImmediate(i64),
     LoggerFailed(io::ErrorKind),
+    /// Writing to the address would have needed more pages of memory than the limit allows
+    MemoryLimitExceeded(u64),
+    /// The instruction budget ran out before the instruction at the address could be executed
+    InstructionBudgetExhausted(u64),
+}
+
+/// Limits on the resources a program can use, to stop it from running forever or using up all
+/// available memory
+#[derive(Debug, Default, Clone, Copy)]
+struct Limits {
+    max_pages: Option<usize>,
+    /// The number of instructions that can still be executed
+    instructions: Option<u64>,
+}
+
+/// A record of the state changed by a single executed instruction, used to undo it
+#[derive(Debug, Clone, Copy)]
+struct JournalEntry {
+    ip: u64,
+    rbo: i64,
+    /// The address written to, and the value it held before
+    write: Option<(u64, i64)>,
+    output: bool,
+}
+
+/// Bounded log of executed instructions, allowing execution to be reversed
+#[derive(Debug, Clone)]
+struct Journal {
+    entries: VecDeque<JournalEntry>,
+    capacity: usize,
+    /// The write performed by the instruction currently being executed
+    pending_write: Option<(u64, i64)>,
+}
+
+/// Information about a single executed instruction, passed to a [`Tracer`]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TraceEvent {
+    /// The instruction pointer before the instruction was executed
+    pub ip: u64,
+    /// The relative base offset before the instruction was executed
+    pub rbo: i64,
+    /// The raw instruction, including modes
+    pub instruction: i64,
+    pub opcode: OpCode,
+    pub modes: [ParamMode; 3],
+    /// The raw parameters, as they were before the instruction was executed
+    pub params: [i64; 3],
+    /// The resolved values of the parameters that were read, or 0 for those that weren't
+    pub values: [i64; 3],
+    /// The address written to and the value written, if the instruction wrote to memory
+    pub write: Option<(u64, i64)>,
+    pub new_ip: u64,
+    pub new_rbo: i64,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let instruction = disasm::Instruction {
+            opcode: self.opcode,
+            modes: self.modes,
+            params: self.params,
+        };
+        let TraceEvent { ip, rbo, .. } = self;
+        write!(
+            fmt,
+            "ip: {ip:>8} | rbo: {rbo:>5} | {:05} [{instruction}]",
+            self.instruction
+        )
+    }
+}
+
+/// Receives a [`TraceEvent`] for every instruction an [`Interpreter`] executes, other than input
+/// instructions that had to wait for input
+pub trait Tracer {
+    fn trace(&mut self, event: &TraceEvent) -> io::Result<()>;
+}
+
+impl<T: Tracer + ?Sized> Tracer for &mut T {
+    fn trace(&mut self, event: &TraceEvent) -> io::Result<()> {
+        (**self).trace(event)
+    }
+}
+
+/// A [`Tracer`] that writes a line of text to a writer for each instruction
+pub struct TextLogger<W: io::Write>(pub W);
+
+impl<W: io::Write> Tracer for TextLogger<W> {
+    fn trace(&mut self, event: &TraceEvent) -> io::Result<()> {
+        writeln!(self.0, "{event}")
+    }
 }
 
 pub struct Interpreter<'a> {
     index: u64,
     rel_offset: i64,
     code: IntcodeMem,
-    logger: Option<&'a mut dyn io::Write>,
+    tracer: Option<Box<dyn Tracer + 'a>>,
+    debugger: Debugger,
+    journal: Option<Journal>,
+    /// Clones share the cache until one of them changes it
+    decode_cache: Option<Rc<DecodeCache>>,
+    limits: Limits,
+    extensions: BTreeMap<u8, Extension<'a>>,
 }
 
+/// Number of addresses that can have decoded instructions cached
+const DECODE_CACHE_SIZE: u64 = 1 << 16;
+
+/// Decoded instructions, indexed by address, along with the raw instruction
+type DecodeCache = Vec<Option<(i64, disasm::Instruction)>>;
+
 impl PartialEq for Interpreter<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.index == other.index && self.rel_offset == other.rel_offset && self.code == other.code
@@ -203,7 +405,12 @@ impl Clone for Interpreter<'_> {
             index: self.index,
             rel_offset: self.rel_offset,
             code: self.code.clone(),
-            logger: None,
+            tracer: None,
+            debugger: self.debugger.clone(),
+            journal: self.journal.clone(),
+            decode_cache: self.decode_cache.clone(),
+            limits: self.limits,
+            extensions: self.extensions.clone(),
         }
     }
 }
@@ -214,14 +421,12 @@ impl fmt::Debug for Interpreter<'_> {
             .field("code", &self.code)
             .field("rbo", &self.rel_offset)
             .field("ip", &self.index)
+            .field("debugger", &self.debugger)
             .field(
-                "logger as *const _",
-                &if let Some(ref logger) = self.logger {
-                    logger as *const _
-                } else {
-                    std::ptr::null()
-                },
+                "journal length",
+                &self.journal.as_ref().map(|j| j.entries.len()),
             )
+            .field("tracing", &self.tracer.is_some())
             .finish()
     }
 }
@@ -255,6 +460,16 @@ impl From<io::Error> for ErrorState {
     }
 }
 
+impl From<ParamMode> for i64 {
+    fn from(mode: ParamMode) -> Self {
+        match mode {
+            ParamMode::Positional => 0,
+            ParamMode::Immediate => 1,
+            ParamMode::Relative => 2,
+        }
+    }
+}
+
 impl TryFrom<i64> for ParamMode {
     type Error = ErrorState;
     fn try_from(i: i64) -> Result<Self, Self::Error> {
@@ -268,41 +483,335 @@ impl TryFrom<i64> for ParamMode {
 }
 
 impl<'a> Interpreter<'a> {
+    /// Log each executed instruction to `logger` as a line of text
     pub fn log_with(&mut self, logger: &'a mut dyn io::Write) {
-        self.logger = Some(logger);
+        self.trace_with(TextLogger(logger));
+    }
+
+    /// Log each executed instruction to stderr
+    ///
+    /// Kept for solutions written against version 1 of this module, which only supported logging
+    /// to stderr.
+    pub fn enable_logging(&mut self) {
+        self.trace_with(TextLogger(io::stderr()));
+    }
+
+    /// Pass each executed instruction to `tracer`, replacing any existing tracer or logger
+    pub fn trace_with(&mut self, tracer: impl Tracer + 'a) {
+        self.tracer = Some(Box::new(tracer));
+    }
+
+    /// Stop tracing or logging, returning the tracer that was in use, if any
+    pub fn stop_tracing(&mut self) -> Option<Box<dyn Tracer + 'a>> {
+        self.tracer.take()
+    }
+
+    /// Add an instruction to the interpreter's instruction set, which runs `handler` when executed
+    ///
+    /// # Example
+    /// ```rust
+    /// use intcode::{Effect, ExtOp, Interpreter, State};
+    /// let mut interpreter = Interpreter::new(vec![1142, 3, 4, 0, 4, 0, 99]);
+    /// let sum = ExtOp { code: 42, mnemonic: "sum", arity: 3, write_param: Some(2) };
+    /// interpreter
+    ///     .register_opcode(sum, |args| Ok(Effect::Write(args[0] + args[1])))
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     interpreter.run_through_inputs(std::iter::empty()).unwrap(),
+    ///     (vec![7], State::Halted)
+    /// );
+    /// ```
+    pub fn register_opcode(
+        &mut self,
+        op: ExtOp,
+        handler: impl Fn(&[i64]) -> Result<Effect, ErrorState> + 'a,
+    ) -> Result<(), ExtError> {
+        if !(10..99).contains(&op.code) || self.extensions.contains_key(&op.code) {
+            return Err(ExtError::Unavailable(op.code));
+        }
+        if op.arity > 3 {
+            return Err(ExtError::InvalidArity(op.arity));
+        }
+        if let Some(n) = op.write_param.filter(|&n| n >= op.arity) {
+            return Err(ExtError::InvalidWriteParam(n));
+        }
+        let handler = Rc::new(handler);
+        self.extensions.insert(op.code, Extension { op, handler });
+        // anything previously decoded with this opcode would have been invalid
+        if let Some(cache) = self.decode_cache.as_mut() {
+            *cache = Rc::default();
+        }
+        Ok(())
     }
 }
 
+/// An Intcode instruction's operation
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum OpCode {
+    Add,
+    Mul,
+    In,
+    Out,
+    Jnz,
+    Jz,
+    Lt,
+    Eq,
+    Rbo,
+    Halt,
+    /// An additional instruction registered with [`Interpreter::register_opcode`]
+    Ext(ExtOp),
+}
+
+/// Description of an additional instruction, registered with [`Interpreter::register_opcode`]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ExtOp {
+    /// The two-digit opcode, which must be between 10 and 98, so as not to clash with a built-in
+    /// instruction
+    pub code: u8,
+    /// The name used for the instruction in logs, disassembly listings and assembly
+    pub mnemonic: &'static str,
+    /// The number of parameters that the instruction takes, up to 3
+    pub arity: usize,
+    /// The index of the parameter that the instruction writes to, if any
+    pub write_param: Option<usize>,
+}
+
+/// What an additional instruction does once its handler has run
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Effect {
+    /// Continue on to the next instruction
+    Continue,
+    /// Store the value in the instruction's write parameter, then continue
+    Write(i64),
+    /// Output the value, then continue
+    Output(i64),
+    /// Jump to the address
+    Jump(u64),
+    Halt,
+}
+
+/// A handler for an additional instruction, which is passed the values of the instruction's
+/// parameters, with 0 in place of the write parameter's value
+pub type Handler<'a> = Rc<dyn Fn(&[i64]) -> Result<Effect, ErrorState> + 'a>;
+
+/// Reasons that an additional instruction can't be registered
 #[derive(Debug, PartialEq, Clone, Copy)]
-enum OpCode {
-    Add = 1,
-    Mul = 2,
-    In = 3,
-    Out = 4,
-    Jnz = 5,
-    Jz = 6,
-    Lt = 7,
-    Eq = 8,
-    Rbo = 9,
-    Halt = 99,
+pub enum ExtError {
+    /// The opcode is outside of the range 10 to 98, or is already registered
+    Unavailable(u8),
+    InvalidArity(usize),
+    InvalidWriteParam(usize),
+}
+
+#[derive(Clone)]
+struct Extension<'a> {
+    op: ExtOp,
+    handler: Handler<'a>,
+}
+
+impl OpCode {
+    /// The name used for the instruction in logs and disassembly listings
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            OpCode::Add => "add",
+            OpCode::Mul => "mul",
+            OpCode::In => "input",
+            OpCode::Out => "output",
+            OpCode::Jnz => "jnz",
+            OpCode::Jz => "jz",
+            OpCode::Lt => "lt",
+            OpCode::Eq => "eq",
+            OpCode::Rbo => "rbo",
+            OpCode::Halt => "halt",
+            OpCode::Ext(ext) => ext.mnemonic,
+        }
+    }
+
+    /// The number that identifies the operation, in the last two digits of an instruction
+    pub fn code(self) -> i64 {
+        match self {
+            OpCode::Add => 1,
+            OpCode::Mul => 2,
+            OpCode::In => 3,
+            OpCode::Out => 4,
+            OpCode::Jnz => 5,
+            OpCode::Jz => 6,
+            OpCode::Lt => 7,
+            OpCode::Eq => 8,
+            OpCode::Rbo => 9,
+            OpCode::Halt => 99,
+            OpCode::Ext(ext) => i64::from(ext.code),
+        }
+    }
+
+    /// The number of parameters that the instruction takes
+    pub fn param_count(self) -> usize {
+        match self {
+            OpCode::Add | OpCode::Mul | OpCode::Lt | OpCode::Eq => 3,
+            OpCode::Jnz | OpCode::Jz => 2,
+            OpCode::In | OpCode::Out | OpCode::Rbo => 1,
+            OpCode::Halt => 0,
+            OpCode::Ext(ext) => ext.arity,
+        }
+    }
+
+    /// The index of the parameter that the instruction writes to, if any
+    pub fn write_param(self) -> Option<usize> {
+        match self {
+            OpCode::Add | OpCode::Mul | OpCode::Lt | OpCode::Eq => Some(2),
+            OpCode::In => Some(0),
+            OpCode::Ext(ext) => ext.write_param,
+            _ => None,
+        }
+    }
+
+    fn from_mnemonic(mnemonic: &str, extensions: &[ExtOp]) -> Option<Self> {
+        [
+            OpCode::Add,
+            OpCode::Mul,
+            OpCode::In,
+            OpCode::Out,
+            OpCode::Jnz,
+            OpCode::Jz,
+            OpCode::Lt,
+            OpCode::Eq,
+            OpCode::Rbo,
+            OpCode::Halt,
+        ]
+        .into_iter()
+        .chain(extensions.iter().copied().map(OpCode::Ext))
+        .find(|op| op.mnemonic() == mnemonic)
+    }
 }
 
 impl Interpreter<'_> {
-    fn param_val(&mut self, param: u64, mode: ParamMode) -> Result<i64, ErrorState> {
+    /// Resolve the value of a raw parameter `param` in `mode`
+    fn param_val(&mut self, param: i64, mode: ParamMode) -> Result<i64, ErrorState> {
         match mode {
             ParamMode::Positional => {
-                let i = self.code.get(param).try_into()?;
+                let i = param.try_into()?;
+                self.check_watch(i, Watch::Read, 0);
                 Ok(self.code.get(i))
             }
-            ParamMode::Immediate => Ok(self.code.get(param)),
+            ParamMode::Immediate => Ok(param),
             ParamMode::Relative => {
-                let i = (self.code.get(param) + self.rel_offset).try_into()?;
+                let i = (param + self.rel_offset).try_into()?;
+                self.check_watch(i, Watch::Read, 0);
                 Ok(self.code.get(i))
             }
         }
     }
 
+    /// Fetch and decode the instruction at the instruction pointer, returning the raw
+    /// instruction along with the decoded form, using the decode cache if it's enabled
+    fn fetch(&mut self) -> Result<(i64, disasm::Instruction), ErrorState> {
+        let cached = self
+            .decode_cache
+            .as_ref()
+            .and_then(|cache| cache.get(usize::try_from(self.index).ok()?).copied());
+        if let Some(Some(decoded)) = cached {
+            return Ok(decoded);
+        }
+
+        let instruction = self.code.get(self.index);
+        let (opcode, modes) = self.decode_op(instruction)?;
+        let mut params = [0; 3];
+        for (i, param) in params.iter_mut().take(opcode.param_count()).enumerate() {
+            *param = self.code.get(self.index + 1 + i as u64);
+        }
+        let decoded = (
+            instruction,
+            disasm::Instruction {
+                opcode,
+                modes,
+                params,
+            },
+        );
+        if let Some(cache) = self.decode_cache.as_mut()
+            && self.index < DECODE_CACHE_SIZE
+        {
+            let cache = Rc::make_mut(cache);
+            let i = self.index as usize;
+            if cache.len() <= i {
+                cache.resize(i + 1, None);
+            }
+            cache[i] = Some(decoded);
+        }
+        Ok(decoded)
+    }
+
+    /// Write to memory, invalidating any cached instructions that include `addr`
+    fn set_mem(&mut self, addr: u64, val: i64) {
+        self.code[addr] = val;
+        if let Some(cache) = self.decode_cache.as_mut() {
+            for i in addr.saturating_sub(3)..=addr {
+                let Ok(i) = usize::try_from(i) else { continue };
+                if cache.get(i).is_some_and(Option::is_some) {
+                    Rc::make_mut(cache)[i] = None;
+                }
+            }
+        }
+    }
+
+    /// Cache decoded instructions, so that they don't need to be fetched from memory and parsed
+    /// each time they're executed. Cached instructions are invalidated when written to, so
+    /// self-modifying code still works. Only instructions at addresses below 65536 are cached.
+    pub fn enable_decode_cache(&mut self) {
+        self.decode_cache.get_or_insert_with(Rc::default);
+    }
+
+    /// Stop caching decoded instructions, and discard the cache
+    pub fn disable_decode_cache(&mut self) {
+        self.decode_cache = None;
+    }
+
+    /// If `addr` is being watched for `access`, record the hit so that execution pauses after
+    /// the current instruction. `new` is ignored for reads.
+    fn check_watch(&mut self, addr: u64, access: Watch, new: i64) {
+        if self.debugger.watch_hit.is_some() {
+            return;
+        }
+        let Some(&watch) = self.debugger.watchpoints.get(&addr) else {
+            return;
+        };
+        let ip = self.index;
+        self.debugger.watch_hit = match (access, watch) {
+            (Watch::Read, Watch::Read | Watch::Access) => Some(Breakpoint::Read { ip, addr }),
+            (Watch::Write, Watch::Write | Watch::Access) => Some(Breakpoint::Write {
+                ip,
+                addr,
+                old: self.code.get(addr),
+                new,
+            }),
+            _ => None,
+        };
+    }
+
     fn parse_op(op: i64) -> Result<(OpCode, [ParamMode; 3]), ErrorState> {
+        Self::parse_op_with(op, &[])
+    }
+
+    /// Parse `op`, recognizing any registered opcodes as well as the built-in ones
+    fn decode_op(&self, op: i64) -> Result<(OpCode, [ParamMode; 3]), ErrorState> {
+        match Self::parse_op(op) {
+            Err(ErrorState::UnrecognizedOpcode(code)) => {
+                match u8::try_from(code)
+                    .ok()
+                    .and_then(|c| self.extensions.get(&c))
+                {
+                    Some(ext) => Self::parse_op_with(op, &[ext.op]),
+                    None => Err(ErrorState::UnrecognizedOpcode(code)),
+                }
+            }
+            decoded => decoded,
+        }
+    }
+
+    /// Parse `op`, recognizing the opcodes in `extensions` as well as the built-in ones
+    fn parse_op_with(
+        op: i64,
+        extensions: &[ExtOp],
+    ) -> Result<(OpCode, [ParamMode; 3]), ErrorState> {
         let modes: [ParamMode; 3] = [
             ((op / 100) % 10).try_into()?,  // C (hundreds place)
             ((op / 1000) % 10).try_into()?, // B (thousands place)
@@ -310,6 +819,11 @@ impl Interpreter<'_> {
         ];
         match op % 100 {
             ..-99 | 100.. => unreachable!("modulo makes this impossible"),
+            code @ 10..99
+                if let Some(&ext) = extensions.iter().find(|ext| i64::from(ext.code) == code) =>
+            {
+                Ok((OpCode::Ext(ext), modes))
+            }
             -99..=0 | 10..99 => Err(ErrorState::UnrecognizedOpcode(op % 100)),
             1 => Ok((OpCode::Add, modes)),
             2 => Ok((OpCode::Mul, modes)),
@@ -326,7 +840,7 @@ impl Interpreter<'_> {
 
     /// Manually set a memory location
     pub fn mem_override(&mut self, location: u64, value: i64) {
-        self.code[location] = value;
+        self.set_mem(location, value);
     }
 
     fn exec_instruction(
@@ -347,33 +861,37 @@ impl Interpreter<'_> {
         // B=1: 2nd parameter is in immediate mode
         // A=0: 3rd parameter is in positional mode (the only valid mode for out parameters)
 
-        let instruction = self.code.get(self.index);
-        // Ensure that instruction is in range - not strictly needed, so only a debug_assert
-        debug_assert!((0..100_000).contains(&instruction));
-
-        let opcode = instruction % 100;
-        let modes: [ParamMode; 3] = [
-            ((instruction / 100) % 10).try_into()?,  // C (hundreds place)
-            ((instruction / 1000) % 10).try_into()?, // B (thousands place)
-            (instruction / 10000).try_into()?,       // A (ten thousands place)
-        ];
+        if self.limits.instructions == Some(0) {
+            return Err(ErrorState::InstructionBudgetExhausted(self.index));
+        }
+        let (instruction, decoded) = self.fetch()?;
+        let disasm::Instruction {
+            opcode,
+            modes,
+            params,
+        } = decoded;
+        let (ip, rbo) = (self.index, self.rel_offset);
+        let mut values = [0; 3];
+        let mut write = None;
 
         /// Shorthand to get the `$n`th parameter's value
         macro_rules! select_by_mode {
-            ($n: literal) => {{ self.param_val(self.index + $n, modes[$n - 1])? }};
+            ($n: expr) => {{
+                let val = self.param_val(params[$n - 1], modes[$n - 1])?;
+                values[$n - 1] = val;
+                val
+            }};
         }
 
         /// Resolves to the destination address pointed to by the `$n`th parameter
         macro_rules! dest {
-            ($n: literal) => {{
+            ($n: expr) => {{
                 match modes[$n - 1] {
-                    ParamMode::Positional => u64::try_from(self.code.get(self.index + $n))?,
+                    ParamMode::Positional => u64::try_from(params[$n - 1])?,
                     ParamMode::Immediate => {
-                        return Err(ErrorState::WriteToImmediate(self.code.get(self.index + $n)));
-                    }
-                    ParamMode::Relative => {
-                        u64::try_from(self.rel_offset + self.code.get(self.index + $n))?
+                        return Err(ErrorState::WriteToImmediate(params[$n - 1]));
                     }
+                    ParamMode::Relative => u64::try_from(self.rel_offset + params[$n - 1])?,
                 }
             }};
         }
@@ -382,7 +900,18 @@ impl Interpreter<'_> {
             ($dest: expr, $new_val: expr) => {{
                 let val: i64 = $new_val;
                 let dest: u64 = $dest;
-                self.code[dest] = val;
+                if let Some(max_pages) = self.limits.max_pages
+                    && !self.code.is_mapped(dest)
+                    && self.code.page_count() >= max_pages
+                {
+                    return Err(ErrorState::MemoryLimitExceeded(dest));
+                }
+                self.check_watch(dest, Watch::Write, val);
+                if let Some(journal) = self.journal.as_mut() {
+                    journal.pending_write = Some((dest, self.code.get(dest)));
+                }
+                self.set_mem(dest, val);
+                write = Some((dest, val));
             }};
         }
 
@@ -391,143 +920,132 @@ impl Interpreter<'_> {
             ($op: expr) => {{ if $op { 1 } else { 0 } }};
         }
 
-        macro_rules! report_op {
-            ($fmt: literal) => {
-                if let Some(ref mut logger) = self.logger {
-                    write!(logger, "ip: {:>8} | rbo: {:>5} | ", self.index, self.rel_offset)?;
-                    writeln!(logger, $fmt)?;
-                }
-            };
-            ($fmt: literal, $($args:tt)*) => {
-                if let Some(ref mut logger) = self.logger {
-                    write!(logger, "ip: {:>8} | rbo: {:>5} | ", self.index, self.rel_offset)?;
-                    logger.write_fmt(format_args!($fmt, $($args)*))?;
-                    writeln!(logger)?;
-                }
-            }
-        }
-
-        macro_rules! report_op4 {
-            ($name: literal) => {
-                report_op!(
-                    "{instruction:05} [{}({}{}, {}{}, {}{})]",
-                    $name,
-                    modes[0],
-                    self.code.get(self.index + 1),
-                    modes[1],
-                    self.code.get(self.index + 2),
-                    modes[2],
-                    self.code.get(self.index + 3)
-                )
-            };
-        }
-
-        macro_rules! report_op3 {
-            ($name: literal) => {
-                report_op!(
-                    "{instruction:05} [{}({}{}, {}{})]",
-                    $name,
-                    modes[0],
-                    self.code.get(self.index + 1),
-                    modes[1],
-                    self.code.get(self.index + 2),
-                )
-            };
-        }
-
-        macro_rules! report_op2 {
-            ($name: literal) => {
-                report_op!(
-                    "{instruction:05} [{}({}{})]",
-                    $name,
-                    modes[0],
-                    self.code.get(self.index + 1),
-                )
-            };
-        }
-
-        match opcode {
-            1 => {
+        let state = match opcode {
+            OpCode::Add => {
                 // add
-                report_op4!("add");
                 set_val!(dest!(3), select_by_mode!(1) + select_by_mode!(2));
                 self.index += 4;
-                Ok(None)
+                None
             }
-            2 => {
+            OpCode::Mul => {
                 // multiply
-                report_op4!("mul");
                 set_val!(dest!(3), select_by_mode!(1) * select_by_mode!(2));
                 self.index += 4;
-                Ok(None)
+                None
             }
-            3 => {
+            OpCode::In => {
                 // input
                 if let Some(input) = inputs.take() {
-                    report_op2!("input");
                     set_val!(dest!(1), input);
                     self.index += 2;
-                    Ok(None)
+                    None
                 } else {
-                    Ok(Some(State::Awaiting))
+                    Some(State::Awaiting)
                 }
             }
-            4 => {
-                report_op2!("output");
+            OpCode::Out => {
                 // output
                 outputs.push(select_by_mode!(1));
                 self.index += 2;
-                Ok(None)
+                None
             }
-            5 => {
-                report_op3!("jnz");
+            OpCode::Jnz => {
                 // jump-if-true
                 if select_by_mode!(1) == 0 {
                     self.index += 3;
-                    Ok(None)
+                    None
                 } else {
                     self.index = select_by_mode!(2).try_into()?;
-                    Ok(None)
+                    None
                 }
             }
-            6 => {
-                report_op3!("jz");
+            OpCode::Jz => {
                 // jump-if-false
                 if select_by_mode!(1) != 0 {
                     self.index += 3;
-                    Ok(None)
+                    None
                 } else {
                     self.index = select_by_mode!(2).try_into()?;
-                    Ok(None)
+                    None
                 }
             }
-            7 => {
-                report_op4!("lt");
+            OpCode::Lt => {
                 // less than
                 set_val!(dest!(3), comp!(select_by_mode!(1) < select_by_mode!(2)));
                 self.index += 4;
-                Ok(None)
+                None
             }
-            8 => {
+            OpCode::Eq => {
                 // equals
-                report_op4!("eq");
                 set_val!(dest!(3), comp!(select_by_mode!(1) == select_by_mode!(2)));
                 self.index += 4;
-                Ok(None)
+                None
             }
-            9 => {
-                report_op2!("rbo");
+            OpCode::Rbo => {
                 // relative base offset
                 self.rel_offset += select_by_mode!(1);
                 self.index += 2;
-                Ok(None)
+                None
             }
-            99 => {
-                report_op!("{instruction:05} [halt]");
-                Ok(Some(State::Halted))
+            OpCode::Halt => Some(State::Halted),
+            OpCode::Ext(ext) => {
+                let mut args = [0; 3];
+                for (i, arg) in args.iter_mut().enumerate().take(ext.arity) {
+                    if ext.write_param != Some(i) {
+                        *arg = select_by_mode!(i + 1);
+                    }
+                }
+                let handler = Rc::clone(&self.extensions[&ext.code].handler);
+                match handler(&args[..ext.arity])? {
+                    Effect::Continue => {
+                        self.index += ext.arity as u64 + 1;
+                        None
+                    }
+                    Effect::Write(val) => {
+                        // checked when the opcode was registered
+                        let Some(n) = ext.write_param else {
+                            unreachable!("{} has no write parameter", ext.mnemonic);
+                        };
+                        set_val!(dest!(n + 1), val);
+                        self.index += ext.arity as u64 + 1;
+                        None
+                    }
+                    Effect::Output(val) => {
+                        outputs.push(val);
+                        self.index += ext.arity as u64 + 1;
+                        None
+                    }
+                    Effect::Jump(addr) => {
+                        self.index = addr;
+                        None
+                    }
+                    Effect::Halt => Some(State::Halted),
+                }
             }
-            i => Err(ErrorState::UnrecognizedOpcode(i)),
+        };
+
+        if state != Some(State::Awaiting)
+            && let Some(budget) = self.limits.instructions.as_mut()
+        {
+            *budget -= 1;
+        }
+        if state != Some(State::Awaiting)
+            && let Some(tracer) = self.tracer.as_mut()
+        {
+            tracer.trace(&TraceEvent {
+                ip,
+                rbo,
+                instruction,
+                opcode,
+                modes,
+                params,
+                values,
+                write,
+                new_ip: self.index,
+                new_rbo: self.rel_offset,
+            })?;
         }
+        Ok(state)
     }
 
     /// Create a new interpreter. Collects `code` into the starting memory state.
@@ -537,8 +1055,13 @@ impl Interpreter<'_> {
         Self {
             index: 0,
             rel_offset: 0,
-            logger: None,
+            tracer: None,
             code: code.into_iter().collect(),
+            debugger: Debugger::default(),
+            journal: None,
+            decode_cache: None,
+            limits: Limits::default(),
+            extensions: BTreeMap::new(),
         }
     }
 
@@ -553,85 +1076,4017 @@ impl Interpreter<'_> {
         let mut inputs = inputs.into_iter();
         let mut current_input = None;
         loop {
-            if current_input.is_none() {
+            if self.debugger.breakpoints.contains(&self.index)
+                && self.debugger.resume_from != Some(self.index)
+            {
+                self.debugger.resume_from = Some(self.index);
+                break Ok((outputs, State::Break(Breakpoint::Address(self.index))));
+            }
+            // Only take input right before an input instruction, so that none is lost if
+            // execution pauses at a breakpoint
+            if current_input.is_none() && self.code.get(self.index) % 100 == OpCode::In.code() {
                 current_input = inputs.next();
             }
-            match self.exec_instruction(&mut current_input, &mut outputs) {
+            match self.step(&mut current_input, &mut outputs) {
                 Ok(None) => (),
-                Ok(Some(State::Halted)) => break Ok((outputs, State::Halted)),
-                Ok(Some(State::Awaiting)) => break Ok((outputs, State::Awaiting)),
+                Ok(Some(state)) => break Ok((outputs, state)),
                 Err(e) => break Err(e),
             }
         }
     }
 
-    /// Pre-compute as much as possible - that is, run every up to, but not including, the first
-    /// In, Out, or Halt instruction, bubbling up any errors that occur.
-    pub fn precompute(&mut self) -> Result<(), ErrorState> {
-        while Self::parse_op(self.code[self.index])
-            .is_ok_and(|(opcode, _)| !matches!(opcode, OpCode::In | OpCode::Out | OpCode::Halt))
-        {
-            self.exec_instruction(&mut None, &mut Vec::with_capacity(0))?;
+    /// Execute exactly one instruction, ignoring any breakpoint on it. If it's an input
+    /// instruction, it takes the value out of `input`, and any output is pushed to `outputs`.
+    ///
+    /// Returns `Ok(None)` if execution can continue, `Ok(Some(state))` if the program halted, is
+    /// awaiting input, or hit a watchpoint, or the error that occurred otherwise.
+    pub fn step(
+        &mut self,
+        input: &mut Option<i64>,
+        outputs: &mut Vec<i64>,
+    ) -> Result<Option<State>, ErrorState> {
+        self.debugger.watch_hit = None;
+        let (ip, rbo, output_count) = (self.index, self.rel_offset, outputs.len());
+        let result = self.exec_instruction(input, outputs);
+        if let Some(journal) = self.journal.as_mut() {
+            let write = journal.pending_write.take();
+            if let Ok(None) = result
+                && journal.capacity > 0
+            {
+                if journal.entries.len() == journal.capacity {
+                    journal.entries.pop_front();
+                }
+                journal.entries.push_back(JournalEntry {
+                    ip,
+                    rbo,
+                    write,
+                    output: outputs.len() > output_count,
+                });
+            }
         }
-        Ok(())
+        if let Ok(None) = result {
+            self.debugger.resume_from = None;
+            if let Some(hit) = self.debugger.watch_hit.take() {
+                return Ok(Some(State::Break(hit)));
+            }
+        }
+        result
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    /// Example program from day 9, which takes no input and outputs its own code
-    #[test]
-    fn quine() {
-        let quine_code = vec![
-            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
-        ];
-        let mut interpreter = Interpreter::new(quine_code.clone());
-        let (outputs, State::Halted) = interpreter.run_through_inputs(Vec::new()).unwrap() else {
-            panic!()
-        };
-        assert_eq!(quine_code, outputs);
+    /// Limit the number of 4 KiB pages of memory the program can use, or remove the limit with
+    /// `None`. Pages that are already in use count towards the limit, and writes that would need
+    /// a new page beyond it fail with [`ErrorState::MemoryLimitExceeded`].
+    ///
+    /// Memory set with [`Interpreter::mem_override`] is not limited.
+    pub fn limit_pages(&mut self, max_pages: Option<usize>) {
+        self.limits.max_pages = max_pages;
     }
 
-    /// Example program from day 9, which "should output a 16-digit number"
-    #[test]
-    fn output_sixteen_digit() {
-        let mut interpreter = Interpreter::new([1102, 34915192, 34915192, 7, 4, 7, 99, 0]);
-        let (outputs, State::Halted) = interpreter.run_through_inputs(Vec::new()).unwrap() else {
-            panic!()
-        };
-        assert_eq!(outputs.len(), 1);
-        assert_eq!(outputs[0].to_string().len(), 16);
+    /// Limit the number of instructions that can be executed from now on, or remove the limit
+    /// with `None`. Once they've been used up, execution fails with
+    /// [`ErrorState::InstructionBudgetExhausted`], and can be resumed by setting a new budget.
+    pub fn set_instruction_budget(&mut self, budget: Option<u64>) {
+        self.limits.instructions = budget;
     }
 
-    /// Example program from day 9, which "should output the large number in the middle"
-    #[test]
-    fn large_number() {
-        let mut interpreter = Interpreter::new([104, 1125899906842624, 99]);
-        let (outputs, State::Halted) = interpreter.run_through_inputs(Vec::new()).unwrap() else {
-            panic!()
-        };
-        assert_eq!(outputs, vec![1125899906842624]);
+    /// The number of instructions that can still be executed, if limited
+    pub fn instruction_budget(&self) -> Option<u64> {
+        self.limits.instructions
     }
 
-    /// Ensure that failure due to missing input leaves the interpreter in a sane state that can
-    /// be recovered from
-    #[test]
-    fn missing_input_recoverable() {
-        let mut interpreter = Interpreter::new(vec![3, 10, 4, 10, 99]);
-        let old_state = interpreter.clone();
+    /// Start recording executed instructions, so that they can be undone with
+    /// [`Interpreter::step_back`] and [`Interpreter::rewind_to_last_output`].
+    ///
+    /// The journal uses at most about `max_bytes` of memory, beyond which the oldest instructions
+    /// are forgotten. Any existing journal is discarded.
+    pub fn enable_journal(&mut self, max_bytes: usize) {
+        let capacity = max_bytes / std::mem::size_of::<JournalEntry>();
+        self.journal = Some(Journal {
+            entries: VecDeque::with_capacity(capacity.min(4096)),
+            capacity,
+            pending_write: None,
+        });
+    }
 
-        let failed_run = interpreter.run_through_inputs(Vec::new());
+    /// Stop recording executed instructions, and discard the journal
+    pub fn disable_journal(&mut self) {
+        self.journal = None;
+    }
 
-        // make sure that the failure returned the right ErrorState and left both `outputs` and
-        // `interpreter` unchanged
-        assert_eq!(failed_run, Ok((vec![], State::Awaiting)));
-        assert_eq!(interpreter, old_state);
+    /// Undo up to `n` instructions, returning how many were actually undone, which is less than
+    /// `n` if the journal runs out.
+    ///
+    /// Inputs consumed and outputs produced by undone instructions are not given back, and
+    /// changes made with [`Interpreter::mem_override`] are not undone.
+    pub fn step_back(&mut self, n: usize) -> usize {
+        for undone in 0..n {
+            let Some(entry) = self.journal.as_mut().and_then(|j| j.entries.pop_back()) else {
+                return undone;
+            };
+            if let Some((addr, old)) = entry.write {
+                self.set_mem(addr, old);
+            }
+            self.index = entry.ip;
+            self.rel_offset = entry.rbo;
+        }
+        n
+    }
 
-        // make sure that interpreter can still be used
-        assert_eq!(
-            interpreter.run_through_inputs(vec![1].into_iter()),
-            Ok((vec![1], State::Halted))
-        );
+    /// Undo instructions until right before the most recent output instruction was executed,
+    /// returning the number of instructions undone, or `None` without changing anything if no
+    /// output instruction is in the journal.
+    pub fn rewind_to_last_output(&mut self) -> Option<usize> {
+        let journal = self.journal.as_ref()?;
+        let distance = journal
+            .entries
+            .iter()
+            .rev()
+            .position(|entry| entry.output)?;
+        Some(self.step_back(distance + 1))
+    }
+
+    /// The current instruction pointer
+    pub fn ip(&self) -> u64 {
+        self.index
+    }
+
+    /// The current relative base offset
+    pub fn rbo(&self) -> i64 {
+        self.rel_offset
+    }
+
+    /// The additional instructions that have been registered, in opcode order
+    pub fn ext_ops(&self) -> Vec<ExtOp> {
+        self.extensions.values().map(|ext| ext.op).collect()
+    }
+
+    /// Read a memory location without triggering any watchpoints
+    pub fn mem_get(&self, location: u64) -> i64 {
+        self.code.get(location)
+    }
+
+    /// The starting addresses of the 512-word pages of memory written to since the interpreter
+    /// was created or [`Interpreter::clear_dirty_pages`] was last called, in order
+    pub fn dirty_pages(&self) -> impl Iterator<Item = u64> {
+        self.code.dirty_pages()
+    }
+
+    /// Reset tracking of which pages have been written to
+    pub fn clear_dirty_pages(&mut self) {
+        self.code.clear_dirty();
+    }
+
+    /// Pause before executing the instruction at `addr`
+    pub fn add_breakpoint(&mut self, addr: u64) {
+        self.debugger.breakpoints.insert(addr);
+    }
+
+    /// Remove the breakpoint at `addr`, returning whether there was one
+    pub fn remove_breakpoint(&mut self, addr: u64) -> bool {
+        self.debugger.breakpoints.remove(&addr)
+    }
+
+    /// Pause after any instruction that accesses `addr` in the way specified by `watch`.
+    /// Replaces any existing watchpoint on `addr`.
+    pub fn add_watchpoint(&mut self, addr: u64, watch: Watch) {
+        self.debugger.watchpoints.insert(addr, watch);
+    }
+
+    /// Remove the watchpoint on `addr`, returning it if there was one
+    pub fn remove_watchpoint(&mut self, addr: u64) -> Option<Watch> {
+        self.debugger.watchpoints.remove(&addr)
+    }
+
+    /// Iterate over the addresses with breakpoints on them
+    pub fn breakpoints(&self) -> impl Iterator<Item = u64> + '_ {
+        self.debugger.breakpoints.iter().copied()
+    }
+
+    /// Iterate over the watched addresses, along with the kinds of access they're watched for
+    pub fn watchpoints(&self) -> impl Iterator<Item = (u64, Watch)> + '_ {
+        self.debugger.watchpoints.iter().map(|(&a, &w)| (a, w))
+    }
+
+    /// Pre-compute as much as possible - that is, run every up to, but not including, the first
+    /// In, Out, or Halt instruction, or registered instruction, bubbling up any errors that occur.
+    ///
+    /// Pre-computed instructions aren't recorded in the journal.
+    pub fn precompute(&mut self) -> Result<(), ErrorState> {
+        while self
+            .decode_op(self.code.get(self.index))
+            .is_ok_and(|(opcode, _)| {
+                !matches!(
+                    opcode,
+                    OpCode::In | OpCode::Out | OpCode::Halt | OpCode::Ext(_)
+                )
+            })
+        {
+            let result = self.exec_instruction(&mut None, &mut Vec::with_capacity(0));
+            if let Some(journal) = self.journal.as_mut() {
+                journal.pending_write = None;
+            }
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Run interactively in Aft Scaffolding Control and Information Interface mode, using terminal
+    /// for I/O.
+    ///
+    /// Panics on I/O errors and Intcode errors - use [`adapters::Ascii::interact`] to handle them
+    /// instead.
+    pub fn ascii_interactive(&mut self) {
+        adapters::Ascii::new(self)
+            .interact(io::stdin().lock(), io::stdout())
+            .expect("ASCII interactive session failed");
+    }
+}
+
+/// Static disassembler, which walks the program from its entry points and follows jumps to
+/// determine which memory locations hold reachable code, and which hold data.
+///
+/// Listings use the same notation as the logger - a mode prefix from [`ParamMode`]'s `Display`
+/// impl, followed by the raw parameter - except that immediate jump targets and positional
+/// references to data are replaced by synthesized labels.
+///
+/// Jumps to positional or relative targets can't be resolved statically, so code only reachable
+/// through them (such as return addresses in programs using the `rbo` stack) is treated as data
+/// unless its address is passed as an additional entry point.
+pub mod disasm {
+    use super::mmu::IntcodeMem;
+    use super::{ExtOp, Interpreter, OpCode, ParamMode};
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::fmt;
+
+    /// A single decoded instruction
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub struct Instruction {
+        pub opcode: OpCode,
+        pub modes: [ParamMode; 3],
+        pub params: [i64; 3],
+    }
+
+    impl Instruction {
+        /// Decode the instruction at `addr`, returning `None` if it's not a valid instruction
+        fn decode(mem: &IntcodeMem, addr: u64, extensions: &[ExtOp]) -> Option<Self> {
+            let (opcode, modes) = Interpreter::parse_op_with(mem.get(addr), extensions).ok()?;
+            let mut params = [0; 3];
+            for (i, param) in params.iter_mut().take(opcode.param_count()).enumerate() {
+                *param = mem.get(addr + 1 + i as u64);
+            }
+            Some(Self {
+                opcode,
+                modes,
+                params,
+            })
+        }
+
+        /// The number of memory locations that the instruction takes up
+        pub fn size(&self) -> u64 {
+            self.opcode.param_count() as u64 + 1
+        }
+
+        /// The (mode, value) pairs of the instruction's parameters
+        pub fn operands(&self) -> impl Iterator<Item = (ParamMode, i64)> + '_ {
+            self.modes
+                .into_iter()
+                .zip(self.params)
+                .take(self.opcode.param_count())
+        }
+
+        /// If the instruction is a jump with an immediate target, returns the target, as well as
+        /// whether the jump is always taken (`Some(true)`), never taken (`Some(false)`), or
+        /// depends on runtime state (`None`).
+        pub fn static_jump(&self) -> Option<(u64, Option<bool>)> {
+            let jump_if_nonzero = match self.opcode {
+                OpCode::Jnz => true,
+                OpCode::Jz => false,
+                _ => return None,
+            };
+            let taken = (self.modes[0] == ParamMode::Immediate)
+                .then(|| (self.params[0] != 0) == jump_if_nonzero);
+            if self.modes[1] != ParamMode::Immediate {
+                return None;
+            }
+            Some((u64::try_from(self.params[1]).ok()?, taken))
+        }
+
+        /// Whether execution can continue on to the following instruction
+        pub fn falls_through(&self) -> bool {
+            match self.opcode {
+                OpCode::Halt => false,
+                OpCode::Jnz | OpCode::Jz => {
+                    self.modes[0] != ParamMode::Immediate
+                        || (self.params[0] != 0) != (self.opcode == OpCode::Jnz)
+                }
+                _ => true,
+            }
+        }
+    }
+
+    impl fmt::Display for Instruction {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(fmt, "{}", self.opcode.mnemonic())?;
+            if self.opcode.param_count() == 0 {
+                return Ok(());
+            }
+            write!(fmt, "(")?;
+            for (i, (mode, val)) in self.operands().enumerate() {
+                if i > 0 {
+                    write!(fmt, ", ")?;
+                }
+                write!(fmt, "{mode}{val}")?;
+            }
+            write!(fmt, ")")
+        }
+    }
+
+    /// An entry in a disassembly listing
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum Item {
+        Code(Instruction),
+        Data(i64),
+    }
+
+    /// The result of statically disassembling an Intcode program
+    #[derive(Debug, Clone)]
+    pub struct Disassembly {
+        items: BTreeMap<u64, Item>,
+        code_labels: BTreeSet<u64>,
+        data_labels: BTreeSet<u64>,
+    }
+
+    impl Disassembly {
+        pub(super) fn walk(
+            mem: &IntcodeMem,
+            entries: impl IntoIterator<Item = u64>,
+            extensions: &[ExtOp],
+        ) -> Self {
+            let mut code: BTreeMap<u64, Instruction> = BTreeMap::new();
+            let mut code_labels = BTreeSet::new();
+            let mut queue: Vec<u64> = Vec::new();
+            for entry in entries {
+                code_labels.insert(entry);
+                queue.push(entry);
+            }
+
+            while let Some(addr) = queue.pop() {
+                // skip anything already decoded, including jumps into the middle of instructions
+                if code
+                    .range(..=addr)
+                    .next_back()
+                    .is_some_and(|(start, instr)| addr < start + instr.size())
+                {
+                    continue;
+                }
+                let Some(instr) = Instruction::decode(mem, addr, extensions) else {
+                    continue;
+                };
+                if let Some((target, taken)) = instr.static_jump()
+                    && taken != Some(false)
+                {
+                    code_labels.insert(target);
+                    queue.push(target);
+                }
+                if instr.falls_through() {
+                    queue.push(addr + instr.size());
+                }
+                code.insert(addr, instr);
+            }
+
+            // only list reachable code and populated memory, so that a lone write far beyond
+            // the program doesn't list every address in between
+            let mut ranges: Vec<(u64, u64)> = code
+                .iter()
+                .map(|(&addr, instr)| (addr, addr + instr.size()))
+                .chain(mem.populated_ranges())
+                .collect();
+            ranges.sort_unstable();
+
+            let mut items = BTreeMap::new();
+            let mut addr = 0;
+            for (start, end) in ranges {
+                addr = addr.max(start);
+                while addr < end {
+                    if let Some(instr) = code.get(&addr) {
+                        items.insert(addr, Item::Code(*instr));
+                        addr += instr.size();
+                    } else {
+                        items.insert(addr, Item::Data(mem.get(addr)));
+                        addr += 1;
+                    }
+                }
+            }
+
+            // labels can only go on the start of an item
+            code_labels.retain(|addr| matches!(items.get(addr), Some(Item::Code(_))));
+            let data_labels = code
+                .values()
+                .flat_map(|instr| instr.operands())
+                .filter(|&(mode, _)| mode == ParamMode::Positional)
+                .filter_map(|(_, val)| u64::try_from(val).ok())
+                .filter(|addr| matches!(items.get(addr), Some(Item::Data(_))))
+                .collect();
+
+            Self {
+                items,
+                code_labels,
+                data_labels,
+            }
+        }
+
+        /// Iterate over the items in the listing, along with their addresses
+        pub fn items(&self) -> impl Iterator<Item = (u64, Item)> + '_ {
+            self.items.iter().map(|(&addr, &item)| (addr, item))
+        }
+
+        /// Get the item starting at `addr`, if any
+        pub fn get(&self, addr: u64) -> Option<Item> {
+            self.items.get(&addr).copied()
+        }
+
+        /// Whether `addr` is part of a reachable instruction
+        pub fn is_code(&self, addr: u64) -> bool {
+            self.items
+                .range(..=addr)
+                .next_back()
+                .is_some_and(|(start, item)| match item {
+                    Item::Code(instr) => addr < start + instr.size(),
+                    Item::Data(_) => false,
+                })
+        }
+
+        /// The synthesized label for `addr`, if it has one
+        pub fn label(&self, addr: u64) -> Option<String> {
+            if self.code_labels.contains(&addr) {
+                Some(format!("code_{addr}"))
+            } else if self.data_labels.contains(&addr) {
+                Some(format!("data_{addr}"))
+            } else {
+                None
+            }
+        }
+
+        /// Format `instr` using labels where possible
+        pub fn fmt_instruction(&self, instr: &Instruction) -> String {
+            let jump_target = instr.static_jump().map(|(target, _)| target);
+            let operands: Vec<String> = instr
+                .operands()
+                .enumerate()
+                .map(|(i, (mode, val))| {
+                    let label = match mode {
+                        ParamMode::Immediate if i == 1 => {
+                            jump_target.filter(|t| self.code_labels.contains(t))
+                        }
+                        ParamMode::Positional => u64::try_from(val)
+                            .ok()
+                            .filter(|addr| self.data_labels.contains(addr)),
+                        _ => None,
+                    };
+                    match label.and_then(|addr| self.label(addr)) {
+                        Some(label) => format!("{mode}{label}"),
+                        None => format!("{mode}{val}"),
+                    }
+                })
+                .collect();
+            if operands.is_empty() {
+                instr.opcode.mnemonic().to_string()
+            } else {
+                format!("{}({})", instr.opcode.mnemonic(), operands.join(", "))
+            }
+        }
+    }
+
+    impl fmt::Display for Disassembly {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            /// Maximum number of values in a single `data` directive
+            const DATA_WIDTH: usize = 8;
+            let mut pending_data: Option<(u64, Vec<String>)> = None;
+
+            macro_rules! flush_data {
+                () => {
+                    if let Some((start, words)) = pending_data.take() {
+                        writeln!(
+                            fmt,
+                            "    {:<40}; {start}",
+                            format!("data({})", words.join(", "))
+                        )?;
+                    }
+                };
+            }
+
+            for (&addr, item) in self.items.iter() {
+                if let Some(label) = self.label(addr) {
+                    flush_data!();
+                    writeln!(fmt, "{label}:")?;
+                }
+                match item {
+                    Item::Code(instr) => {
+                        flush_data!();
+                        writeln!(fmt, "    {:<40}; {addr}", self.fmt_instruction(instr))?;
+                    }
+                    Item::Data(val) => {
+                        let (_, words) = pending_data.get_or_insert_with(|| (addr, Vec::new()));
+                        words.push(val.to_string());
+                        if words.len() == DATA_WIDTH {
+                            flush_data!();
+                        }
+                    }
+                }
+            }
+            flush_data!();
+            Ok(())
+        }
+    }
+
+    /// Disassemble `code`, starting from each of the addresses in `entries`
+    pub fn disassemble(
+        code: impl IntoIterator<Item = i64>,
+        entries: impl IntoIterator<Item = u64>,
+    ) -> Disassembly {
+        Disassembly::walk(&code.into_iter().collect(), entries, &[])
+    }
+
+    impl Interpreter<'_> {
+        /// Disassemble the interpreter's current memory, starting from the current instruction
+        /// pointer, as well as any addresses in `extra_entries`, using the mnemonics of any
+        /// registered instructions
+        pub fn disassemble(&self, extra_entries: impl IntoIterator<Item = u64>) -> Disassembly {
+            let entries = std::iter::once(self.index).chain(extra_entries);
+            Disassembly::walk(&self.code, entries, &self.ext_ops())
+        }
+    }
+}
+
+/// Assembler for the textual format used by [`disasm`] listings, so that a listing can be edited
+/// and turned back into a program.
+///
+/// Each line holds any number of `label:` definitions, followed by an optional statement, which
+/// is either an instruction such as `add(p100, 1, r-2)`, or a `data(...)` directive containing
+/// numbers or labels to store as-is. Instructions without parameters, such as `halt`, can omit
+/// the parentheses, and everything after a `;` is a comment.
+///
+/// Operands use the mode prefixes from [`ParamMode`]'s `Display` impl, and can be either numbers
+/// or labels, which resolve to the address they're defined at. Because `p` and `r` are always
+/// taken to be prefixes, a label used as an immediate operand can't start with either of them.
+pub mod asm {
+    use super::{ExtOp, OpCode, ParamMode};
+    use std::collections::HashMap;
+    use std::fmt;
+
+    #[derive(Debug, PartialEq, Clone)]
+    pub enum AsmErrorKind {
+        UnknownMnemonic(String),
+        WrongParamCount {
+            mnemonic: String,
+            expected: usize,
+            found: usize,
+        },
+        WriteToImmediate,
+        InvalidOperand(String),
+        DuplicateLabel(String),
+        UndefinedLabel(String),
+        UnexpectedText(String),
+        UnclosedParen,
+    }
+
+    /// An error encountered while assembling, with the 1-indexed line and column it occurred at
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct AsmError {
+        pub line: usize,
+        pub column: usize,
+        pub kind: AsmErrorKind,
+    }
+
+    impl fmt::Display for AsmError {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(fmt, "{}:{}: ", self.line, self.column)?;
+            match &self.kind {
+                AsmErrorKind::UnknownMnemonic(m) => write!(fmt, "unknown mnemonic {m:?}"),
+                AsmErrorKind::WrongParamCount {
+                    mnemonic,
+                    expected,
+                    found,
+                } => write!(
+                    fmt,
+                    "{mnemonic} takes {expected} parameter(s), but {found} were provided"
+                ),
+                AsmErrorKind::WriteToImmediate => write!(fmt, "can't write to immediate operand"),
+                AsmErrorKind::InvalidOperand(op) => write!(fmt, "invalid operand {op:?}"),
+                AsmErrorKind::DuplicateLabel(l) => write!(fmt, "label {l:?} defined twice"),
+                AsmErrorKind::UndefinedLabel(l) => write!(fmt, "label {l:?} is not defined"),
+                AsmErrorKind::UnexpectedText(t) => write!(fmt, "unexpected text {t:?}"),
+                AsmErrorKind::UnclosedParen => write!(fmt, "missing closing parenthesis"),
+            }
+        }
+    }
+
+    impl std::error::Error for AsmError {}
+
+    /// The value of an operand, before labels are resolved
+    enum Value<'a> {
+        Num(i64),
+        Label(&'a str),
+    }
+
+    fn is_label(s: &str) -> bool {
+        s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    fn parse_value(s: &str) -> Option<Value<'_>> {
+        if let Ok(n) = s.parse() {
+            Some(Value::Num(n))
+        } else if is_label(s) {
+            Some(Value::Label(s))
+        } else {
+            None
+        }
+    }
+
+    /// Split the comma-separated contents of a pair of parentheses, returning each trimmed
+    /// operand with its offset within `line`
+    fn split_operands(line: &str, start: usize, end: usize) -> Vec<(usize, &str)> {
+        let inner = &line[start..end];
+        if inner.trim().is_empty() {
+            return Vec::new();
+        }
+        let mut operands = Vec::new();
+        let mut offset = start;
+        for part in inner.split(',') {
+            let trimmed = part.trim_start();
+            operands.push((offset + part.len() - trimmed.len(), trimmed.trim_end()));
+            offset += part.len() + 1;
+        }
+        operands
+    }
+
+    /// Assemble `src` into a program that can be passed to [`super::Interpreter::new`]
+    pub fn assemble(src: &str) -> Result<Vec<i64>, AsmError> {
+        assemble_with(src, &[])
+    }
+
+    /// Assemble `src` as with [`assemble`], also accepting the mnemonics of `extensions`
+    pub fn assemble_with(src: &str, extensions: &[ExtOp]) -> Result<Vec<i64>, AsmError> {
+        let mut code: Vec<i64> = Vec::new();
+        let mut labels: HashMap<&str, u64> = HashMap::new();
+        // locations that need to be filled in with a label's address once all are defined
+        let mut fixups: Vec<(usize, &str, AsmError)> = Vec::new();
+
+        for (line_no, full_line) in src.lines().enumerate() {
+            let line = full_line.split(';').next().unwrap_or_default();
+            let err = |offset: usize, kind: AsmErrorKind| AsmError {
+                line: line_no + 1,
+                column: line[..offset].chars().count() + 1,
+                kind,
+            };
+
+            let mut pos = 0;
+            // find any label definitions and the mnemonic
+            let (mnemonic, mnemonic_pos) = loop {
+                pos = line.len() - line[pos..].trim_start().len();
+                if pos == line.len() {
+                    break ("", pos);
+                }
+                let word_len = line[pos..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(line.len() - pos);
+                let word = &line[pos..pos + word_len];
+                let rest = line[pos + word_len..].trim_start();
+                if let Some(after_colon) = rest.strip_prefix(':') {
+                    if !is_label(word) {
+                        return Err(err(pos, AsmErrorKind::InvalidOperand(word.to_string())));
+                    }
+                    if labels.insert(word, code.len() as u64).is_some() {
+                        return Err(err(pos, AsmErrorKind::DuplicateLabel(word.to_string())));
+                    }
+                    pos = line.len() - after_colon.len();
+                } else {
+                    break (word, pos);
+                }
+            };
+            if mnemonic_pos == line.len() {
+                continue;
+            }
+            if mnemonic.is_empty() {
+                return Err(err(
+                    mnemonic_pos,
+                    AsmErrorKind::UnexpectedText(line[mnemonic_pos..].trim_end().to_string()),
+                ));
+            }
+
+            // find the operands, if there are any
+            pos = mnemonic_pos + mnemonic.len();
+            let after_mnemonic = line[pos..].trim_start();
+            let operands = if let Some(inner) = after_mnemonic.strip_prefix('(') {
+                let open = line.len() - inner.len();
+                let Some(close) = inner.find(')').map(|i| open + i) else {
+                    return Err(err(open - 1, AsmErrorKind::UnclosedParen));
+                };
+                let trailing = line[close + 1..].trim();
+                if !trailing.is_empty() {
+                    let trailing_pos = close + 1 + line[close + 1..].find(trailing).unwrap();
+                    return Err(err(
+                        trailing_pos,
+                        AsmErrorKind::UnexpectedText(trailing.to_string()),
+                    ));
+                }
+                split_operands(line, open, close)
+            } else if after_mnemonic.trim_end().is_empty() {
+                Vec::new()
+            } else {
+                let trailing_pos = line.len() - after_mnemonic.len();
+                return Err(err(
+                    trailing_pos,
+                    AsmErrorKind::UnexpectedText(after_mnemonic.trim_end().to_string()),
+                ));
+            };
+
+            macro_rules! push_value {
+                ($offset: expr, $value: expr) => {
+                    match $value {
+                        Value::Num(n) => code.push(n),
+                        Value::Label(label) => {
+                            fixups.push((
+                                code.len(),
+                                label,
+                                err($offset, AsmErrorKind::UndefinedLabel(label.to_string())),
+                            ));
+                            code.push(0);
+                        }
+                    }
+                };
+            }
+
+            if mnemonic == "data" {
+                for (offset, text) in operands {
+                    let Some(value) = parse_value(text) else {
+                        return Err(err(offset, AsmErrorKind::InvalidOperand(text.to_string())));
+                    };
+                    push_value!(offset, value);
+                }
+                continue;
+            }
+
+            let Some(opcode) = OpCode::from_mnemonic(mnemonic, extensions) else {
+                return Err(err(
+                    mnemonic_pos,
+                    AsmErrorKind::UnknownMnemonic(mnemonic.to_string()),
+                ));
+            };
+            if operands.len() != opcode.param_count() {
+                return Err(err(
+                    mnemonic_pos,
+                    AsmErrorKind::WrongParamCount {
+                        mnemonic: mnemonic.to_string(),
+                        expected: opcode.param_count(),
+                        found: operands.len(),
+                    },
+                ));
+            }
+
+            let mut parsed = Vec::with_capacity(operands.len());
+            let mut instruction = opcode.code();
+            let mut place = 100;
+            for (i, &(offset, text)) in operands.iter().enumerate() {
+                let (mode, value_text) = match text.split_at_checked(1) {
+                    Some(("p", rest)) => (ParamMode::Positional, rest),
+                    Some(("r", rest)) => (ParamMode::Relative, rest),
+                    _ => (ParamMode::Immediate, text),
+                };
+                if mode == ParamMode::Immediate && opcode.write_param() == Some(i) {
+                    return Err(err(offset, AsmErrorKind::WriteToImmediate));
+                }
+                let Some(value) = parse_value(value_text) else {
+                    return Err(err(offset, AsmErrorKind::InvalidOperand(text.to_string())));
+                };
+                instruction += i64::from(mode) * place;
+                place *= 10;
+                parsed.push((offset, value));
+            }
+            code.push(instruction);
+            for (offset, value) in parsed {
+                push_value!(offset, value);
+            }
+        }
+
+        for (index, label, error) in fixups {
+            let Some(&addr) = labels.get(label) else {
+                return Err(error);
+            };
+            code[index] = addr as i64;
+        }
+        Ok(code)
+    }
+}
+
+/// Saving and restoring interpreter state, using a compact, versioned binary format.
+///
+/// All integers are little-endian. A snapshot starts with the 7 bytes `INTCODE`, followed by a
+/// 1-byte format version, then the following for version 1:
+///
+/// * the instruction pointer, as a `u64`
+/// * the relative base offset, as an `i64`
+/// * the number of pages that follow, as a `u32`
+///
+/// Each page consists of its starting address as a `u64`, the number of words stored as a `u16`,
+/// then the words themselves as `i64`s. Trailing zeros within a page are left out, so pages only
+/// containing zeros are stored with no words, which keeps them allocated when restored.
+///
+/// Breakpoints, watchpoints and loggers are not part of the machine state, so are not saved.
+pub mod snapshot {
+    use super::Interpreter;
+    use super::mmu::IntcodeMem;
+    use std::io::{self, Read, Write};
+    use std::rc::Rc;
+
+    pub(super) const MAGIC: &[u8; 7] = b"INTCODE";
+    pub const VERSION: u8 = 1;
+
+    #[derive(Debug)]
+    pub enum SnapshotError {
+        Io(io::Error),
+        BadMagic,
+        UnsupportedVersion(u8),
+        /// A page started at a misaligned address, or claimed to hold more than 512 words
+        InvalidPage(u64),
+    }
+
+    impl From<io::Error> for SnapshotError {
+        fn from(err: io::Error) -> Self {
+            Self::Io(err)
+        }
+    }
+
+    fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+        let mut buf = [0; N];
+        reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    impl Interpreter<'_> {
+        /// Write the interpreter's state to `writer`
+        pub fn save_snapshot(&self, writer: &mut impl Write) -> io::Result<()> {
+            let pages: Vec<_> = self.code.pages().collect();
+            writer.write_all(MAGIC)?;
+            writer.write_all(&[VERSION])?;
+            writer.write_all(&self.index.to_le_bytes())?;
+            writer.write_all(&self.rel_offset.to_le_bytes())?;
+            writer.write_all(&(pages.len() as u32).to_le_bytes())?;
+            for (addr, page) in pages {
+                let len = page.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+                writer.write_all(&addr.to_le_bytes())?;
+                writer.write_all(&(len as u16).to_le_bytes())?;
+                for word in &page[..len] {
+                    writer.write_all(&word.to_le_bytes())?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Replace the interpreter's state with a snapshot read from `reader`, keeping its
+        /// logger, breakpoints and watchpoints. The journal is emptied, as its entries don't
+        /// apply to the restored state, and no pages are considered dirty.
+        ///
+        /// If an error occurs, the interpreter is left unchanged.
+        pub fn restore_snapshot(&mut self, reader: &mut impl Read) -> Result<(), SnapshotError> {
+            if &read_bytes(reader)? != MAGIC {
+                return Err(SnapshotError::BadMagic);
+            }
+            let [version] = read_bytes(reader)?;
+            if version != VERSION {
+                return Err(SnapshotError::UnsupportedVersion(version));
+            }
+            let index = u64::from_le_bytes(read_bytes(reader)?);
+            let rel_offset = i64::from_le_bytes(read_bytes(reader)?);
+            let page_count = u32::from_le_bytes(read_bytes(reader)?);
+
+            let mut pages = Vec::new();
+            for _ in 0..page_count {
+                let addr = u64::from_le_bytes(read_bytes(reader)?);
+                let len = u16::from_le_bytes(read_bytes(reader)?);
+                if addr % 512 != 0 || len > 512 {
+                    return Err(SnapshotError::InvalidPage(addr));
+                }
+                let mut page = [0; 512];
+                for word in &mut page[..usize::from(len)] {
+                    *word = i64::from_le_bytes(read_bytes(reader)?);
+                }
+                pages.push((addr, page));
+            }
+            let code = IntcodeMem::from_pages(pages);
+
+            self.index = index;
+            self.rel_offset = rel_offset;
+            self.code = code;
+            if let Some(cache) = self.decode_cache.as_mut() {
+                *cache = Rc::default();
+            }
+            if let Some(journal) = self.journal.as_mut() {
+                journal.entries.clear();
+                journal.pending_write = None;
+            }
+            Ok(())
+        }
+
+        /// Create a new interpreter from a snapshot read from `reader`
+        pub fn from_snapshot(reader: &mut impl Read) -> Result<Self, SnapshotError> {
+            let mut interpreter = Interpreter::new(std::iter::empty());
+            interpreter.restore_snapshot(reader)?;
+            Ok(interpreter)
+        }
+    }
+}
+
+/// Loading Intcode programs from files, standard input, or anything else that can be read, in any
+/// of the following formats, which are told apart automatically:
+///
+/// * comma-separated values, as puzzle inputs are given, with any whitespace around each value
+/// * the text format read by [`asm::assemble`]
+/// * the binary format described in [`snapshot`], which also restores the instruction pointer and
+///   relative base offset
+///
+/// # Example
+/// ```rust
+/// use intcode::{State, load};
+/// let mut interpreter = load::from_bytes(b"104, 1024,\n99\n").unwrap();
+/// assert_eq!(
+///     interpreter.run_through_inputs(std::iter::empty()).unwrap(),
+///     (vec![1024], State::Halted)
+/// );
+/// ```
+pub mod load {
+    use super::Interpreter;
+    use super::asm::{self, AsmError};
+    use super::snapshot::{self, SnapshotError};
+    use std::fmt;
+    use std::io::{self, Read};
+    use std::path::Path;
+
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum Format {
+        Csv,
+        Asm,
+        Snapshot,
+    }
+
+    #[derive(Debug)]
+    pub enum LoadError {
+        Io(io::Error),
+        /// A comma-separated value couldn't be parsed, with its 0-indexed position and its text
+        InvalidValue {
+            index: usize,
+            text: String,
+        },
+        /// The input wasn't a snapshot, but wasn't valid UTF-8 text either
+        NotText,
+        Asm(AsmError),
+        Snapshot(SnapshotError),
+    }
+
+    impl fmt::Display for LoadError {
+        fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                LoadError::Io(err) => write!(fmt, "{err}"),
+                LoadError::InvalidValue { index, text } => {
+                    write!(fmt, "value {index} ({text:?}) is not a valid integer")
+                }
+                LoadError::NotText => write!(fmt, "not a snapshot or UTF-8 text"),
+                LoadError::Asm(err) => write!(fmt, "{err}"),
+                LoadError::Snapshot(err) => write!(fmt, "invalid snapshot: {err:?}"),
+            }
+        }
+    }
+
+    impl std::error::Error for LoadError {}
+
+    impl From<io::Error> for LoadError {
+        fn from(err: io::Error) -> Self {
+            Self::Io(err)
+        }
+    }
+
+    /// Guess the format of `bytes` - anything that isn't a snapshot, and contains characters
+    /// other than digits, signs, commas and whitespace is assumed to be assembly
+    pub fn detect(bytes: &[u8]) -> Format {
+        if bytes.starts_with(snapshot::MAGIC) {
+            Format::Snapshot
+        } else if bytes
+            .iter()
+            .all(|b| b.is_ascii_digit() || b.is_ascii_whitespace() || b"-+,".contains(b))
+        {
+            Format::Csv
+        } else {
+            Format::Asm
+        }
+    }
+
+    /// Parse comma-separated values
+    pub fn parse_csv(text: &str) -> Result<Vec<i64>, LoadError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(Vec::new());
+        }
+        text.split(',')
+            .map(str::trim)
+            .enumerate()
+            .map(|(index, text)| {
+                text.parse().map_err(|_| LoadError::InvalidValue {
+                    index,
+                    text: text.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Load a program from `bytes`, in whichever format it's in
+    pub fn from_bytes<'a>(bytes: &[u8]) -> Result<Interpreter<'a>, LoadError> {
+        let format = detect(bytes);
+        if format == Format::Snapshot {
+            return Interpreter::from_snapshot(&mut &bytes[..]).map_err(LoadError::Snapshot);
+        }
+        let text = str::from_utf8(bytes).map_err(|_| LoadError::NotText)?;
+        let code = match format {
+            Format::Csv => parse_csv(text)?,
+            _ => asm::assemble(text).map_err(LoadError::Asm)?,
+        };
+        Ok(Interpreter::new(code))
+    }
+
+    /// Load a program from `reader`, in whichever format it's in
+    pub fn from_reader<'a>(mut reader: impl Read) -> Result<Interpreter<'a>, LoadError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        from_bytes(&bytes)
+    }
+
+    /// Load a program from the file at `path`, or from standard input if `path` is `-`
+    pub fn from_path<'a>(path: impl AsRef<Path>) -> Result<Interpreter<'a>, LoadError> {
+        let path = path.as_ref();
+        if path == Path::new("-") {
+            from_reader(io::stdin().lock())
+        } else {
+            from_bytes(&std::fs::read(path)?)
+        }
+    }
+}
+
+/// Profiling of Intcode programs, using a [`Tracer`] that counts executed instructions
+///
+/// # Example
+/// ```rust
+/// use intcode::{Interpreter, profile::Profiler};
+/// let mut profiler = Profiler::default();
+/// let mut interpreter = Interpreter::new(vec![104, 1024, 99]);
+/// let disassembly = interpreter.disassemble([]);
+/// interpreter.trace_with(&mut profiler);
+/// interpreter.run_through_inputs(std::iter::empty()).unwrap();
+/// drop(interpreter);
+/// print!("{}", profiler.report(10, Some(&disassembly)));
+/// ```
+pub mod profile {
+    use super::disasm::{Disassembly, Item};
+    use super::{OpCode, TraceEvent, Tracer};
+    use std::collections::HashMap;
+    use std::fmt::Write;
+    use std::io;
+
+    /// The number of times a conditional jump was and was not taken
+    #[derive(Debug, Default, PartialEq, Clone, Copy)]
+    pub struct BranchCounts {
+        pub taken: u64,
+        pub not_taken: u64,
+    }
+
+    #[derive(Debug, Default, Clone)]
+    pub struct Profiler {
+        total: u64,
+        by_address: HashMap<u64, u64>,
+        by_opcode: HashMap<OpCode, u64>,
+        branches: HashMap<u64, BranchCounts>,
+    }
+
+    impl Tracer for Profiler {
+        fn trace(&mut self, event: &TraceEvent) -> io::Result<()> {
+            self.total += 1;
+            *self.by_address.entry(event.ip).or_default() += 1;
+            *self.by_opcode.entry(event.opcode).or_default() += 1;
+            if matches!(event.opcode, OpCode::Jnz | OpCode::Jz) {
+                let counts = self.branches.entry(event.ip).or_default();
+                if event.new_ip == event.ip + 3 {
+                    counts.not_taken += 1;
+                } else {
+                    counts.taken += 1;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Profiler {
+        /// The total number of instructions executed
+        pub fn total(&self) -> u64 {
+            self.total
+        }
+
+        /// The number of times the instruction at `addr` was executed
+        pub fn count_at(&self, addr: u64) -> u64 {
+            self.by_address.get(&addr).copied().unwrap_or_default()
+        }
+
+        /// The number of times instructions with `opcode` were executed
+        pub fn count_of(&self, opcode: OpCode) -> u64 {
+            self.by_opcode.get(&opcode).copied().unwrap_or_default()
+        }
+
+        /// The branch counts for the conditional jump at `addr`, if it was ever executed
+        pub fn branch_counts(&self, addr: u64) -> Option<BranchCounts> {
+            self.branches.get(&addr).copied()
+        }
+
+        /// The executed addresses and how many times each was executed, most-executed first
+        pub fn hot_spots(&self) -> Vec<(u64, u64)> {
+            let mut spots: Vec<(u64, u64)> =
+                self.by_address.iter().map(|(&a, &c)| (a, c)).collect();
+            spots.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            spots
+        }
+
+        /// A human-readable report of the `limit` most-executed instructions, and of how many
+        /// times each opcode was executed. If `disassembly` is provided, hot spots are annotated
+        /// with the instructions at those addresses.
+        pub fn report(&self, limit: usize, disassembly: Option<&Disassembly>) -> String {
+            let percent = |count: u64| count as f64 * 100.0 / self.total.max(1) as f64;
+            let mut report = String::new();
+            macro_rules! report_line {
+                ($($args:tt)*) => {
+                    writeln!(report, $($args)*).expect("writing to a String can't fail")
+                };
+            }
+
+            report_line!("{} instructions executed", self.total);
+            report_line!();
+            report_line!("by opcode:");
+            let mut opcodes: Vec<(OpCode, u64)> =
+                self.by_opcode.iter().map(|(&op, &c)| (op, c)).collect();
+            opcodes.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.mnemonic().cmp(b.0.mnemonic())));
+            for (opcode, count) in opcodes {
+                report_line!(
+                    "    {:<8} {count:>12} {:>6.2}%",
+                    opcode.mnemonic(),
+                    percent(count)
+                );
+            }
+            report_line!();
+            report_line!("hot spots:");
+            for (addr, count) in self.hot_spots().into_iter().take(limit) {
+                let mut entry = format!("    {addr:>8}: {count:>12} {:>6.2}%", percent(count));
+                if let Some(BranchCounts { taken, not_taken }) = self.branch_counts(addr) {
+                    write!(entry, " [taken {taken}, not taken {not_taken}]")
+                        .expect("writing to a String can't fail");
+                }
+                if let Some(disassembly) = disassembly
+                    && let Some(Item::Code(instr)) = disassembly.get(addr)
+                {
+                    write!(entry, "    {}", disassembly.fmt_instruction(&instr))
+                        .expect("writing to a String can't fail");
+                }
+                report_line!("{entry}");
+            }
+            report
+        }
+    }
+}
+
+/// Analyses for reverse-engineering Intcode programs.
+///
+/// [`Taint`] is a [`Tracer`] that tracks which memory cells, outputs and branches depend on which
+/// inputs as a program runs. [`Cfg`] is a static control-flow graph, which recognizes function
+/// calls made using the usual `rbo` stack idiom - pushing the return address onto the stack with
+/// an instruction like `add(ret, 0, r0)`, then unconditionally jumping to the function, which
+/// returns by unconditionally jumping to a relative-mode target. It can be exported to Graphviz
+/// DOT format, optionally highlighting the branches that a [`Taint`] found to depend on input.
+///
+/// # Example
+/// ```rust
+/// use intcode::{Interpreter, analysis::Taint};
+/// let mut taint = Taint::default();
+/// let mut interpreter = Interpreter::new(vec![3, 11, 1005, 11, 7, 104, 0, 4, 11, 99, 0, 0]);
+/// interpreter.trace_with(&mut taint);
+/// interpreter.run_through_inputs([5]).unwrap();
+/// let cfg = interpreter.cfg([]);
+/// drop(interpreter);
+/// assert!(taint.input_branches().any(|(addr, _)| addr == 2));
+/// print!("{}", cfg.to_dot(Some(&taint)));
+/// ```
+pub mod analysis {
+    use super::disasm::{Disassembly, Instruction, Item};
+    use super::mmu::IntcodeMem;
+    use super::{ExtOp, Interpreter, OpCode, ParamMode, TraceEvent, Tracer};
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+    use std::fmt::Write;
+    use std::io;
+
+    /// The indices of the inputs that a value was derived from
+    pub type Inputs = BTreeSet<usize>;
+
+    /// A [`Tracer`] that tracks which values were derived from input.
+    ///
+    /// Values are tainted by the inputs that they were computed from, either directly or through
+    /// values read using a relative base that was itself derived from input. Taint only flows
+    /// through data, so values computed in a branch that depended on input aren't tainted unless
+    /// they were also computed from input.
+    #[derive(Debug, Default, Clone)]
+    pub struct Taint {
+        inputs_read: usize,
+        cells: HashMap<u64, Inputs>,
+        rbo: Inputs,
+        outputs: Vec<Inputs>,
+        branches: BTreeMap<u64, Inputs>,
+    }
+
+    impl Tracer for Taint {
+        fn trace(&mut self, event: &TraceEvent) -> io::Result<()> {
+            let mut sources = Inputs::new();
+            for (i, (mode, param)) in event
+                .modes
+                .into_iter()
+                .zip(event.params)
+                .take(event.opcode.param_count())
+                .enumerate()
+            {
+                if event.opcode.write_param() == Some(i) {
+                    continue;
+                }
+                let addr = match mode {
+                    ParamMode::Immediate => continue,
+                    ParamMode::Positional => param,
+                    ParamMode::Relative => {
+                        sources.extend(&self.rbo);
+                        param + event.rbo
+                    }
+                };
+                if let Some(taint) = u64::try_from(addr).ok().and_then(|a| self.cells.get(&a)) {
+                    sources.extend(taint);
+                }
+            }
+
+            match event.opcode {
+                OpCode::In => {
+                    sources = Inputs::from([self.inputs_read]);
+                    self.inputs_read += 1;
+                }
+                OpCode::Out => self.outputs.push(std::mem::take(&mut sources)),
+                OpCode::Jnz | OpCode::Jz if !sources.is_empty() => {
+                    let branch = self.branches.entry(event.ip).or_default();
+                    branch.append(&mut sources);
+                }
+                OpCode::Rbo => self.rbo.append(&mut sources),
+                _ => (),
+            }
+            if let Some((addr, _)) = event.write {
+                if sources.is_empty() {
+                    self.cells.remove(&addr);
+                } else {
+                    self.cells.insert(addr, sources);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Taint {
+        /// The number of inputs read so far
+        pub fn inputs_read(&self) -> usize {
+            self.inputs_read
+        }
+
+        /// The inputs that the value currently at `addr` was derived from, if any
+        pub fn taint_of(&self, addr: u64) -> Option<&Inputs> {
+            self.cells.get(&addr)
+        }
+
+        /// Iterate over the memory cells currently holding values derived from input, in address
+        /// order
+        pub fn tainted_cells(&self) -> impl Iterator<Item = (u64, &Inputs)> {
+            let cells: BTreeMap<_, _> = self.cells.iter().map(|(&addr, t)| (addr, t)).collect();
+            cells.into_iter()
+        }
+
+        /// The inputs that each output so far was derived from, in order
+        pub fn outputs(&self) -> &[Inputs] {
+            &self.outputs
+        }
+
+        /// Iterate over the addresses of conditional jumps whose condition or target was derived
+        /// from input at least once, along with the inputs involved, in address order
+        pub fn input_branches(&self) -> impl Iterator<Item = (u64, &Inputs)> {
+            self.branches.iter().map(|(&addr, inputs)| (addr, inputs))
+        }
+    }
+
+    /// How control leaves a basic block
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum Exit {
+        /// Continues on into the block at the address
+        Fallthrough(u64),
+        /// Unconditionally jumps to the address
+        Jump(u64),
+        /// Conditionally jumps to `taken`, which is `None` if it's computed at runtime, or
+        /// continues on to `not_taken`
+        Branch {
+            taken: Option<u64>,
+            not_taken: u64,
+        },
+        /// Calls the function at `target`, which returns to `ret`
+        Call {
+            target: u64,
+            ret: u64,
+        },
+        /// Returns to the address on top of the `rbo` stack
+        Return,
+        /// Unconditionally jumps to a computed address that isn't a recognized return
+        Dynamic,
+        Halt,
+    }
+
+    impl Exit {
+        /// The addresses that control can pass to within the same function
+        fn local_successors(self) -> impl Iterator<Item = u64> {
+            let (a, b) = match self {
+                Exit::Fallthrough(addr) | Exit::Jump(addr) | Exit::Call { ret: addr, .. } => {
+                    (Some(addr), None)
+                }
+                Exit::Branch { taken, not_taken } => (taken, Some(not_taken)),
+                Exit::Return | Exit::Dynamic | Exit::Halt => (None, None),
+            };
+            a.into_iter().chain(b)
+        }
+    }
+
+    /// A straight-line sequence of instructions, from `start` up to but not including `end`
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Block {
+        pub start: u64,
+        pub end: u64,
+        pub exit: Exit,
+    }
+
+    /// A function, consisting of the blocks reachable from its entry point without following
+    /// calls
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Function {
+        pub entry: u64,
+        /// The start addresses of the function's blocks
+        pub blocks: BTreeSet<u64>,
+    }
+
+    /// A control-flow graph of an Intcode program
+    #[derive(Debug, Clone)]
+    pub struct Cfg {
+        disassembly: Disassembly,
+        blocks: BTreeMap<u64, Block>,
+        functions: BTreeMap<u64, Function>,
+        back_edges: BTreeSet<(u64, u64)>,
+    }
+
+    /// If `push` stores an immediate value computed from immediate operands into a relative-mode
+    /// address, the value stored
+    fn pushed_value(push: &Instruction) -> Option<i64> {
+        if push.modes
+            != [
+                ParamMode::Immediate,
+                ParamMode::Immediate,
+                ParamMode::Relative,
+            ]
+        {
+            return None;
+        }
+        match push.opcode {
+            OpCode::Add => push.params[0].checked_add(push.params[1]),
+            OpCode::Mul => push.params[0].checked_mul(push.params[1]),
+            _ => None,
+        }
+    }
+
+    /// Find the calls in `disassembly`, mapping the address of the jump to the function's address
+    /// and the return address
+    fn find_calls(disassembly: &Disassembly) -> BTreeMap<u64, (u64, u64)> {
+        let mut calls = BTreeMap::new();
+        let mut prev: Option<(u64, Instruction)> = None;
+        for (addr, item) in disassembly.items() {
+            let Item::Code(instr) = item else {
+                prev = None;
+                continue;
+            };
+            let ret = addr + instr.size();
+            if let Some((target, Some(true))) = instr.static_jump()
+                && let Some((prev_addr, push)) = prev
+                && prev_addr + push.size() == addr
+                && pushed_value(&push) == i64::try_from(ret).ok()
+            {
+                calls.insert(addr, (target, ret));
+            }
+            prev = Some((addr, instr));
+        }
+        calls
+    }
+
+    impl Cfg {
+        fn build(
+            mem: &IntcodeMem,
+            entries: impl IntoIterator<Item = u64>,
+            extensions: &[ExtOp],
+        ) -> Self {
+            let entries: BTreeSet<u64> = entries.into_iter().collect();
+            // return addresses are only reachable through dynamic jumps, so keep disassembling
+            // with them as extra entry points until no new calls are found
+            let mut extra_entries = BTreeSet::new();
+            let (disassembly, calls) = loop {
+                let disassembly =
+                    Disassembly::walk(mem, entries.union(&extra_entries).copied(), extensions);
+                let calls = find_calls(&disassembly);
+                let returns: BTreeSet<u64> = calls.values().map(|&(_, ret)| ret).collect();
+                if returns.is_subset(&extra_entries) {
+                    break (disassembly, calls);
+                }
+                extra_entries.extend(returns);
+            };
+
+            let mut leaders: BTreeSet<u64> = entries.union(&extra_entries).copied().collect();
+            for (addr, item) in disassembly.items() {
+                if let Item::Code(instr) = item
+                    && matches!(instr.opcode, OpCode::Jnz | OpCode::Jz | OpCode::Halt)
+                {
+                    leaders.insert(addr + instr.size());
+                    leaders.extend(instr.static_jump().map(|(target, _)| target));
+                }
+            }
+
+            let mut blocks = BTreeMap::new();
+            let mut current: Option<u64> = None;
+            let mut items = disassembly.items().peekable();
+            while let Some((addr, item)) = items.next() {
+                let Item::Code(instr) = item else {
+                    current = None;
+                    continue;
+                };
+                let start = *current.get_or_insert(addr);
+                let end = addr + instr.size();
+                let next_is_leader = match items.peek() {
+                    Some(&(next, Item::Code(_))) => leaders.contains(&next),
+                    _ => true,
+                };
+                let exit = match (instr.opcode, instr.static_jump()) {
+                    (OpCode::Halt, _) => Exit::Halt,
+                    (OpCode::Jnz | OpCode::Jz, Some((target, Some(true)))) => {
+                        match calls.get(&addr) {
+                            Some(&(target, ret)) => Exit::Call { target, ret },
+                            None => Exit::Jump(target),
+                        }
+                    }
+                    (OpCode::Jnz | OpCode::Jz, Some((_, Some(false)))) => Exit::Fallthrough(end),
+                    (OpCode::Jnz | OpCode::Jz, Some((target, None))) => Exit::Branch {
+                        taken: Some(target),
+                        not_taken: end,
+                    },
+                    (OpCode::Jnz | OpCode::Jz, None) if instr.falls_through() => Exit::Branch {
+                        taken: None,
+                        not_taken: end,
+                    },
+                    (OpCode::Jnz | OpCode::Jz, None) if instr.modes[1] == ParamMode::Relative => {
+                        Exit::Return
+                    }
+                    (OpCode::Jnz | OpCode::Jz, None) => Exit::Dynamic,
+                    _ if next_is_leader => Exit::Fallthrough(end),
+                    _ => continue,
+                };
+                blocks.insert(start, Block { start, end, exit });
+                current = None;
+            }
+            drop(items);
+
+            let function_entries = entries
+                .iter()
+                .copied()
+                .chain(calls.values().map(|&(target, _)| target));
+            let mut functions = BTreeMap::new();
+            let mut back_edges = BTreeSet::new();
+            for entry in function_entries {
+                if functions.contains_key(&entry) || !blocks.contains_key(&entry) {
+                    continue;
+                }
+                let mut function = Function {
+                    entry,
+                    blocks: BTreeSet::new(),
+                };
+                // depth-first search, where edges to blocks on the stack close loops
+                let mut on_stack = BTreeSet::from([entry]);
+                let mut stack = vec![(entry, blocks[&entry].exit.local_successors())];
+                function.blocks.insert(entry);
+                while let Some((block, successors)) = stack.last_mut() {
+                    let block = *block;
+                    match successors.find(|s| blocks.contains_key(s)) {
+                        Some(next) if on_stack.contains(&next) => {
+                            back_edges.insert((block, next));
+                        }
+                        Some(next) if function.blocks.insert(next) => {
+                            on_stack.insert(next);
+                            stack.push((next, blocks[&next].exit.local_successors()));
+                        }
+                        Some(_) => (),
+                        None => {
+                            on_stack.remove(&block);
+                            stack.pop();
+                        }
+                    }
+                }
+                functions.insert(entry, function);
+            }
+
+            Self {
+                disassembly,
+                blocks,
+                functions,
+                back_edges,
+            }
+        }
+
+        /// The disassembly that the graph was built from, including the code found by following
+        /// calls
+        pub fn disassembly(&self) -> &Disassembly {
+            &self.disassembly
+        }
+
+        /// Iterate over the basic blocks, in address order
+        pub fn blocks(&self) -> impl Iterator<Item = &Block> {
+            self.blocks.values()
+        }
+
+        /// Get the block starting at `addr`, if any
+        pub fn block(&self, addr: u64) -> Option<&Block> {
+            self.blocks.get(&addr)
+        }
+
+        /// Iterate over the functions, in order of their entry points
+        pub fn functions(&self) -> impl Iterator<Item = &Function> {
+            self.functions.values()
+        }
+
+        /// The edges that close loops, as pairs of block start addresses
+        pub fn back_edges(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+            self.back_edges.iter().copied()
+        }
+
+        /// Render the graph in Graphviz DOT format, with each function in its own cluster, loops
+        /// drawn in bold, and blocks ending in branches that `taint` found to depend on input
+        /// highlighted in red
+        pub fn to_dot(&self, taint: Option<&Taint>) -> String {
+            let input_branches: BTreeMap<u64, &Inputs> =
+                taint.map_or_else(BTreeMap::new, |taint| taint.input_branches().collect());
+            let mut dot = String::from("digraph intcode {\n");
+            dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+            // blocks shared between functions are drawn in the first function they're part of
+            let mut drawn = BTreeSet::new();
+            for function in self.functions.values() {
+                let _ = writeln!(dot, "    subgraph cluster_{} {{", function.entry);
+                let _ = writeln!(dot, "        label=\"function at {}\";", function.entry);
+                for &start in &function.blocks {
+                    if drawn.insert(start) {
+                        self.write_block(&mut dot, &self.blocks[&start], &input_branches);
+                    }
+                }
+                dot.push_str("    }\n");
+            }
+            for block in self.blocks.values() {
+                if !drawn.contains(&block.start) {
+                    self.write_block(&mut dot, block, &input_branches);
+                }
+            }
+
+            for block in self.blocks.values() {
+                let mut edge = |to: u64, attrs: &[&str]| {
+                    if !self.blocks.contains_key(&to) {
+                        return;
+                    }
+                    let mut attrs = attrs.to_vec();
+                    if self.back_edges.contains(&(block.start, to)) {
+                        attrs.push("style=bold");
+                    }
+                    let _ = write!(dot, "    b{} -> b{to}", block.start);
+                    if !attrs.is_empty() {
+                        let _ = write!(dot, " [{}]", attrs.join(", "));
+                    }
+                    dot.push_str(";\n");
+                };
+                match block.exit {
+                    Exit::Fallthrough(to) | Exit::Jump(to) => edge(to, &[]),
+                    Exit::Branch { taken, not_taken } => {
+                        if let Some(taken) = taken {
+                            edge(taken, &["label=\"taken\""]);
+                        }
+                        edge(not_taken, &["label=\"not taken\""]);
+                    }
+                    Exit::Call { target, ret } => {
+                        edge(target, &["label=\"call\"", "style=dashed"]);
+                        edge(ret, &["label=\"return\""]);
+                    }
+                    Exit::Return | Exit::Dynamic | Exit::Halt => (),
+                }
+            }
+            dot.push_str("}\n");
+            dot
+        }
+
+        fn write_block(
+            &self,
+            dot: &mut String,
+            block: &Block,
+            input_branches: &BTreeMap<u64, &Inputs>,
+        ) {
+            let mut label = String::new();
+            let mut highlight = None;
+            if let Some(name) = self.disassembly.label(block.start) {
+                let _ = write!(label, "{name}:\\l");
+            }
+            let items = self
+                .disassembly
+                .items()
+                .skip_while(|&(a, _)| a < block.start);
+            for (addr, item) in items.take_while(|&(a, _)| a < block.end) {
+                if let Item::Code(instr) = item {
+                    let _ = write!(
+                        label,
+                        "{addr:>6}: {}\\l",
+                        self.disassembly.fmt_instruction(&instr)
+                    );
+                }
+                if let Some(inputs) = input_branches.get(&addr) {
+                    highlight = Some(inputs);
+                }
+            }
+            match block.exit {
+                Exit::Return => label.push_str("(return)\\l"),
+                Exit::Dynamic => label.push_str("(computed jump)\\l"),
+                _ => (),
+            }
+            let _ = write!(dot, "        b{} [label=\"{label}\"", block.start);
+            if let Some(inputs) = highlight {
+                let inputs: Vec<String> = inputs.iter().map(usize::to_string).collect();
+                let _ = write!(
+                    dot,
+                    ", color=red, tooltip=\"depends on inputs {}\"",
+                    inputs.join(", ")
+                );
+            }
+            dot.push_str("];\n");
+        }
+    }
+
+    /// Build a control-flow graph of `code`, starting from each of the addresses in `entries`,
+    /// each of which is treated as a function
+    pub fn cfg(code: impl IntoIterator<Item = i64>, entries: impl IntoIterator<Item = u64>) -> Cfg {
+        Cfg::build(&code.into_iter().collect(), entries, &[])
+    }
+
+    impl Interpreter<'_> {
+        /// Build a control-flow graph of the interpreter's current memory, starting from the
+        /// current instruction pointer, as well as any addresses in `extra_entries`
+        pub fn cfg(&self, extra_entries: impl IntoIterator<Item = u64>) -> Cfg {
+            let entries = std::iter::once(self.index).chain(extra_entries);
+            Cfg::build(&self.code, entries, &self.ext_ops())
+        }
+    }
+}
+
+/// A runtime for networks of interpreters that communicate by sending each other messages.
+///
+/// A [`Protocol`] describes how each node's output is split into messages and where they go,
+/// and how messages are turned back into input. Messages that aren't sent to another node are
+/// collected for the caller to handle with [`Network::take_external`].
+pub mod network {
+    use super::{ErrorState, Interpreter, State};
+    use std::collections::VecDeque;
+
+    /// Where a message is sent
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum Destination {
+        Node(usize),
+        /// Outside of the network, to be taken with [`Network::take_external`]
+        External,
+    }
+
+    pub trait Protocol {
+        type Message;
+        /// The number of output values making up a single message
+        const FRAME_LEN: usize;
+
+        /// Decode a frame output by the node at index `from`, returning `None` if it's invalid.
+        ///
+        /// As this takes `&mut self`, it can also be used as a hook to observe or record traffic.
+        fn decode(&mut self, from: usize, frame: &[i64]) -> Option<(Destination, Self::Message)>;
+
+        /// Encode a message as input for the node receiving it
+        fn encode(&self, message: &Self::Message, input: &mut VecDeque<i64>);
+
+        /// Input to provide to a node that's waiting for input when none is queued, or `None` to
+        /// leave it waiting
+        fn idle_input(&self) -> Option<i64> {
+            None
+        }
+    }
+
+    /// How to choose which nodes to run
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum Scheduling {
+        /// Run every node that hasn't halted each round, in order
+        RoundRobin,
+        /// Only run nodes that have input queued, or have not yet been run
+        EventDriven,
+    }
+
+    /// The reason that [`Network::run`] stopped
+    #[derive(Debug, PartialEq, Clone, Copy)]
+    pub enum NetworkState {
+        /// Every node has halted
+        Halted,
+        /// There are messages for [`Network::take_external`]
+        External,
+        /// No messages are in flight, and every running node is repeatedly receiving idle input
+        Idle,
+        /// No messages are in flight, and every running node is waiting for input that will
+        /// never come
+        Deadlocked,
+    }
+
+    #[derive(Debug, PartialEq)]
+    pub enum NetworkErrorKind {
+        Intcode(ErrorState),
+        BadFrame(Vec<i64>),
+        NoSuchNode(usize),
+    }
+
+    /// An error caused by the node at index `node`
+    #[derive(Debug, PartialEq)]
+    pub struct NetworkError {
+        pub node: usize,
+        pub kind: NetworkErrorKind,
+    }
+
+    /// Number of consecutive rounds without any traffic needed to consider a network using idle
+    /// input to be idle
+    const IDLE_ROUNDS: usize = 2;
+
+    struct Node<'a> {
+        interpreter: Interpreter<'a>,
+        inputs: VecDeque<i64>,
+        /// Output not yet making up a full frame
+        outputs: Vec<i64>,
+        started: bool,
+        halted: bool,
+    }
+
+    pub struct Network<'a, P: Protocol> {
+        protocol: P,
+        nodes: Vec<Node<'a>>,
+        scheduling: Scheduling,
+        external: Vec<P::Message>,
+        quiet_rounds: usize,
+    }
+
+    impl<'a, P: Protocol> Network<'a, P> {
+        /// Create a network with a node for each of `interpreters`, in order
+        pub fn new(
+            protocol: P,
+            interpreters: impl IntoIterator<Item = Interpreter<'a>>,
+            scheduling: Scheduling,
+        ) -> Self {
+            let nodes = interpreters
+                .into_iter()
+                .map(|interpreter| Node {
+                    interpreter,
+                    inputs: VecDeque::new(),
+                    outputs: Vec::new(),
+                    started: false,
+                    halted: false,
+                })
+                .collect();
+            Self {
+                protocol,
+                nodes,
+                scheduling,
+                external: Vec::new(),
+                quiet_rounds: 0,
+            }
+        }
+
+        pub fn protocol(&self) -> &P {
+            &self.protocol
+        }
+
+        pub fn protocol_mut(&mut self) -> &mut P {
+            &mut self.protocol
+        }
+
+        /// The interpreter for the node at index `node`
+        pub fn interpreter(&self, node: usize) -> &Interpreter<'a> {
+            &self.nodes[node].interpreter
+        }
+
+        /// Whether the node at index `node` has halted
+        pub fn halted(&self, node: usize) -> bool {
+            self.nodes[node].halted
+        }
+
+        /// Queue raw input for the node at index `node`, such as an address or configuration
+        pub fn push_input(&mut self, node: usize, input: impl IntoIterator<Item = i64>) {
+            self.nodes[node].inputs.extend(input);
+        }
+
+        /// Send `message` to the node at index `node` from outside of the network
+        pub fn send(&mut self, node: usize, message: &P::Message) {
+            self.protocol.encode(message, &mut self.nodes[node].inputs);
+        }
+
+        /// Take all messages sent outside of the network so far
+        pub fn take_external(&mut self) -> Vec<P::Message> {
+            std::mem::take(&mut self.external)
+        }
+
+        /// Run the node at index `i` until it halts or needs more input, routing its output.
+        /// Returns whether any messages were sent or non-idle input was consumed.
+        fn run_node(&mut self, i: usize) -> Result<bool, NetworkError> {
+            let idle_input = self.protocol.idle_input();
+            let node = &mut self.nodes[i];
+            node.started = true;
+            let queued = node.inputs.len();
+            let mut idle = if queued == 0 { idle_input } else { None };
+            let inputs = &mut node.inputs;
+            let (outputs, state) = node
+                .interpreter
+                .run_through_inputs(std::iter::from_fn(|| {
+                    inputs.pop_front().or_else(|| idle.take())
+                }))
+                .map_err(|e| NetworkError {
+                    node: i,
+                    kind: NetworkErrorKind::Intcode(e),
+                })?;
+            node.halted = state == State::Halted;
+            let mut active = node.inputs.len() < queued;
+            node.outputs.extend(outputs);
+
+            let frames: Vec<Vec<i64>> = node
+                .outputs
+                .chunks_exact(P::FRAME_LEN)
+                .map(<[i64]>::to_vec)
+                .collect();
+            node.outputs.drain(..frames.len() * P::FRAME_LEN);
+            for frame in frames {
+                active = true;
+                let Some((dest, message)) = self.protocol.decode(i, &frame) else {
+                    return Err(NetworkError {
+                        node: i,
+                        kind: NetworkErrorKind::BadFrame(frame),
+                    });
+                };
+                match dest {
+                    Destination::Node(n) if n < self.nodes.len() => {
+                        self.protocol.encode(&message, &mut self.nodes[n].inputs)
+                    }
+                    Destination::Node(n) => {
+                        return Err(NetworkError {
+                            node: i,
+                            kind: NetworkErrorKind::NoSuchNode(n),
+                        });
+                    }
+                    Destination::External => self.external.push(message),
+                }
+            }
+            Ok(active)
+        }
+
+        /// Run a single round of scheduling, returning whether there was any traffic
+        pub fn run_round(&mut self) -> Result<bool, NetworkError> {
+            let mut active = false;
+            for i in 0..self.nodes.len() {
+                let node = &self.nodes[i];
+                let runnable = !node.halted
+                    && match self.scheduling {
+                        Scheduling::RoundRobin => true,
+                        Scheduling::EventDriven => !node.started || !node.inputs.is_empty(),
+                    };
+                if runnable {
+                    active |= self.run_node(i)?;
+                }
+            }
+            Ok(active)
+        }
+
+        /// Run until every node halts, a message is sent outside of the network, or the network
+        /// becomes idle or deadlocked
+        pub fn run(&mut self) -> Result<NetworkState, NetworkError> {
+            loop {
+                let active = self.run_round()?;
+                if !self.external.is_empty() {
+                    self.quiet_rounds = 0;
+                    return Ok(NetworkState::External);
+                }
+                if self.nodes.iter().all(|node| node.halted) {
+                    return Ok(NetworkState::Halted);
+                }
+                if active {
+                    self.quiet_rounds = 0;
+                    continue;
+                }
+                self.quiet_rounds += 1;
+                if self.protocol.idle_input().is_none() {
+                    self.quiet_rounds = 0;
+                    return Ok(NetworkState::Deadlocked);
+                }
+                if self.quiet_rounds >= IDLE_ROUNDS || self.scheduling == Scheduling::EventDriven {
+                    self.quiet_rounds = 0;
+                    return Ok(NetworkState::Idle);
+                }
+            }
+        }
+    }
+}
+
+/// Adapters for the I/O conventions that Intcode programs use, which encode input and decode
+/// output, reporting output that doesn't follow the convention as an error.
+///
+/// # Example
+/// ```rust
+/// use intcode::{Interpreter, State, adapters::Ascii};
+/// let mut interpreter = Interpreter::new(vec![104, 72, 104, 105, 104, 10, 104, 1024, 99]);
+/// let output = Ascii::new(&mut interpreter).run().unwrap();
+/// assert_eq!(output.text, "Hi\n");
+/// assert_eq!(output.final_value, Some(1024));
+/// assert_eq!(output.state, State::Halted);
+/// ```
+pub mod adapters {
+    use super::{ErrorState, Interpreter, State};
+    use std::collections::{HashMap, VecDeque};
+    use std::io::{self, BufRead, Write};
+
+    #[derive(Debug)]
+    pub enum AdapterError {
+        Intcode(ErrorState),
+        Io(io::Error),
+        /// Input for an ASCII program contained a non-ASCII character
+        NonAsciiInput(char),
+        /// An ASCII program output a non-ASCII value, then continued to output more
+        NonAsciiOutput(i64),
+        /// The program halted partway through outputting a tuple, leaving these values
+        IncompleteTuple(Vec<i64>),
+    }
+
+    impl From<ErrorState> for AdapterError {
+        fn from(err: ErrorState) -> Self {
+            Self::Intcode(err)
+        }
+    }
+
+    impl From<io::Error> for AdapterError {
+        fn from(err: io::Error) -> Self {
+            Self::Io(err)
+        }
+    }
+
+    /// Run `interpreter`, only taking values from `input` as they're needed, so that any left
+    /// over remain queued
+    fn run_queued(
+        interpreter: &mut Interpreter<'_>,
+        input: &mut VecDeque<i64>,
+    ) -> Result<(Vec<i64>, State), ErrorState> {
+        interpreter.run_through_inputs(std::iter::from_fn(|| input.pop_front()))
+    }
+
+    /// Output from a program using [`Ascii`]
+    #[derive(Debug, PartialEq)]
+    pub struct AsciiOutput {
+        pub text: String,
+        /// A value outside of the ASCII range that was output last, which programs use to report
+        /// a result that can't be represented as a character
+        pub final_value: Option<i64>,
+        pub state: State,
+    }
+
+    impl AsciiOutput {
+        pub fn lines(&self) -> std::str::Lines<'_> {
+            self.text.lines()
+        }
+    }
+
+    /// Adapter for programs that take and produce lines of ASCII text
+    pub struct Ascii<'i, 'a> {
+        interpreter: &'i mut Interpreter<'a>,
+        input: VecDeque<i64>,
+    }
+
+    impl<'i, 'a> Ascii<'i, 'a> {
+        pub fn new(interpreter: &'i mut Interpreter<'a>) -> Self {
+            Self {
+                interpreter,
+                input: VecDeque::new(),
+            }
+        }
+
+        /// Queue `line` as input, followed by a newline
+        pub fn send_line(&mut self, line: &str) -> Result<(), AdapterError> {
+            if let Some(c) = line.chars().find(|c| !c.is_ascii()) {
+                return Err(AdapterError::NonAsciiInput(c));
+            }
+            self.input
+                .extend(line.bytes().chain(std::iter::once(b'\n')).map(i64::from));
+            Ok(())
+        }
+
+        /// Run until the program halts, waits for input after using up the queued input, or hits
+        /// a breakpoint
+        pub fn run(&mut self) -> Result<AsciiOutput, AdapterError> {
+            let (output, state) = run_queued(self.interpreter, &mut self.input)?;
+            let mut text = String::with_capacity(output.len());
+            let mut final_value = None;
+            for i in output {
+                if let Some(value) = final_value {
+                    return Err(AdapterError::NonAsciiOutput(value));
+                }
+                match u8::try_from(i) {
+                    Ok(b) if b.is_ascii() => text.push(char::from(b)),
+                    _ => final_value = Some(i),
+                }
+            }
+            Ok(AsciiOutput {
+                text,
+                final_value,
+                state,
+            })
+        }
+
+        /// Send `line`, then run, as with [`Ascii::send_line`] and [`Ascii::run`]
+        pub fn command(&mut self, line: &str) -> Result<AsciiOutput, AdapterError> {
+            self.send_line(line)?;
+            self.run()
+        }
+
+        /// Run interactively, writing output to `output`, and reading a line from `input` whenever
+        /// the program is waiting for input.
+        ///
+        /// Returns once the program halts, or once `input` runs out, in which case the state is
+        /// [`State::Awaiting`]. A final non-ASCII value is written as a number on its own line.
+        pub fn interact(
+            &mut self,
+            mut input: impl BufRead,
+            mut output: impl Write,
+        ) -> Result<State, AdapterError> {
+            let mut line = String::new();
+            loop {
+                let AsciiOutput {
+                    text,
+                    final_value,
+                    state,
+                } = self.run()?;
+                output.write_all(text.as_bytes())?;
+                if let Some(value) = final_value {
+                    writeln!(output, "{value}")?;
+                }
+                output.flush()?;
+                match state {
+                    State::Halted => return Ok(state),
+                    // if paused by a breakpoint, resume without waiting for a line of input
+                    State::Break(_) => continue,
+                    State::Awaiting => (),
+                }
+                line.clear();
+                if input.read_line(&mut line)? == 0 {
+                    return Ok(State::Awaiting);
+                }
+                self.send_line(line.strip_suffix('\n').unwrap_or(&line))?;
+            }
+        }
+    }
+
+    /// Adapter for programs that output fixed-size tuples of values, such as the `(x, y, tile)`
+    /// triples of 2019 day 13, or the `(address, x, y)` packets of 2019 day 23
+    pub struct Tuples<'i, 'a, const N: usize> {
+        interpreter: &'i mut Interpreter<'a>,
+        input: VecDeque<i64>,
+        /// Values of a tuple that was only partially output before the program paused
+        partial: Vec<i64>,
+    }
+
+    impl<'i, 'a, const N: usize> Tuples<'i, 'a, N> {
+        pub fn new(interpreter: &'i mut Interpreter<'a>) -> Self {
+            Self {
+                interpreter,
+                input: VecDeque::new(),
+                partial: Vec::with_capacity(N),
+            }
+        }
+
+        /// Queue `values` as input
+        pub fn send(&mut self, values: impl IntoIterator<Item = i64>) {
+            self.input.extend(values);
+        }
+
+        /// Run until the program halts, waits for input after using up the queued input, or hits
+        /// a breakpoint, returning the tuples output.
+        ///
+        /// If the program pauses partway through a tuple, the rest of it is expected from the
+        /// next run, but if it halts, that's an error.
+        pub fn run(&mut self) -> Result<(Vec<[i64; N]>, State), AdapterError> {
+            let (output, state) = run_queued(self.interpreter, &mut self.input)?;
+            let mut tuples = Vec::with_capacity((self.partial.len() + output.len()) / N);
+            for i in output {
+                self.partial.push(i);
+                if let Ok(tuple) = <[i64; N]>::try_from(self.partial.as_slice()) {
+                    tuples.push(tuple);
+                    self.partial.clear();
+                }
+            }
+            if state == State::Halted && !self.partial.is_empty() {
+                return Err(AdapterError::IncompleteTuple(std::mem::take(
+                    &mut self.partial,
+                )));
+            }
+            Ok((tuples, state))
+        }
+    }
+
+    /// A sparse grid of values, indexed by `(x, y)`
+    #[derive(Debug, PartialEq, Clone)]
+    pub struct Grid<T> {
+        cells: HashMap<(i64, i64), T>,
+    }
+
+    impl<T> Default for Grid<T> {
+        fn default() -> Self {
+            Self {
+                cells: HashMap::new(),
+            }
+        }
+    }
+
+    impl<T> Grid<T> {
+        pub fn get(&self, x: i64, y: i64) -> Option<&T> {
+            self.cells.get(&(x, y))
+        }
+
+        /// Set the value at `(x, y)`, returning the previous value, if any
+        pub fn set(&mut self, x: i64, y: i64, value: T) -> Option<T> {
+            self.cells.insert((x, y), value)
+        }
+
+        pub fn len(&self) -> usize {
+            self.cells.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.cells.is_empty()
+        }
+
+        /// Iterate over the cells that have been set, in no particular order
+        pub fn iter(&self) -> impl Iterator<Item = ((i64, i64), &T)> {
+            self.cells.iter().map(|(&pos, value)| (pos, value))
+        }
+
+        /// The smallest and largest coordinates of any set cell, or `None` if the grid is empty
+        pub fn bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+            let mut positions = self.cells.keys();
+            let &first = positions.next()?;
+            Some(positions.fold(
+                (first, first),
+                |((min_x, min_y), (max_x, max_y)), &(x, y)| {
+                    ((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y)))
+                },
+            ))
+        }
+
+        /// Render the grid as lines of text, with `render` choosing the character for each cell
+        /// within the bounds, whether set or not
+        pub fn render(&self, mut render: impl FnMut(Option<&T>) -> char) -> String {
+            let Some(((min_x, min_y), (max_x, max_y))) = self.bounds() else {
+                return String::new();
+            };
+            let mut s = String::new();
+            for y in min_y..=max_y {
+                s.extend((min_x..=max_x).map(|x| render(self.get(x, y))));
+                s.push('\n');
+            }
+            s
+        }
+    }
+
+    impl Grid<u8> {
+        /// Parse ASCII art, such as the camera output of 2019 day 17, with the first character of
+        /// the first line at `(0, 0)`, and `y` increasing downwards
+        pub fn from_ascii(text: &str) -> Self {
+            let mut grid = Self::default();
+            for (y, line) in (0..).zip(text.lines()) {
+                for (x, b) in (0..).zip(line.bytes()) {
+                    grid.set(x, y, b);
+                }
+            }
+            grid
+        }
+    }
+
+    /// Adapter for programs that draw by outputting `(x, y, value)` triples, painting them onto a
+    /// [`Grid`] as they come
+    pub struct GridPainter<'i, 'a> {
+        tuples: Tuples<'i, 'a, 3>,
+        grid: Grid<i64>,
+    }
+
+    impl<'i, 'a> GridPainter<'i, 'a> {
+        pub fn new(interpreter: &'i mut Interpreter<'a>) -> Self {
+            Self {
+                tuples: Tuples::new(interpreter),
+                grid: Grid::default(),
+            }
+        }
+
+        /// Queue `values` as input
+        pub fn send(&mut self, values: impl IntoIterator<Item = i64>) {
+            self.tuples.send(values);
+        }
+
+        /// Run as with [`Tuples::run`], painting each triple onto the grid
+        pub fn run(&mut self) -> Result<State, AdapterError> {
+            self.run_with(|_, _, _| ())
+        }
+
+        /// Run as with [`GridPainter::run`], calling `on_paint` with each triple before it's
+        /// painted, for programs that output values that aren't meant to be drawn, or to animate
+        /// the drawing
+        pub fn run_with(
+            &mut self,
+            mut on_paint: impl FnMut(i64, i64, i64),
+        ) -> Result<State, AdapterError> {
+            let (triples, state) = self.tuples.run()?;
+            for [x, y, value] in triples {
+                on_paint(x, y, value);
+                self.grid.set(x, y, value);
+            }
+            Ok(state)
+        }
+
+        pub fn grid(&self) -> &Grid<i64> {
+            &self.grid
+        }
+
+        pub fn into_grid(self) -> Grid<i64> {
+            self.grid
+        }
+    }
+}
+
+/// Building blocks for robots controlled by Intcode programs, which move around a grid and report
+/// what they find, such as the hull painting robot of 2019 day 11, the repair droid of day 15, and
+/// the vacuum robot of day 17.
+///
+/// Maps are accumulated in an [`adapters::Grid`](super::adapters::Grid), with a tile type chosen
+/// by the caller. Robots that are steered by the caller are described by a [`Protocol`], and can
+/// be used to map out their surroundings with [`explore`], while robots that steer themselves are
+/// described by a [`Turtle`], and run with [`drive`].
+///
+/// # Example
+/// ```rust
+/// use intcode::Interpreter;
+/// use intcode::robot::{self, Direction, Protocol, Search};
+///
+/// struct Droid;
+/// impl Protocol for Droid {
+///     type Tile = bool;
+///     fn command(&self, dir: Direction) -> i64 {
+///         dir as i64
+///     }
+///     fn status(&self, value: i64) -> Option<bool> {
+///         Some(value == 1)
+///     }
+///     fn is_passable(&self, open: bool) -> bool {
+///         open
+///     }
+/// }
+///
+/// // a droid that can't go anywhere, as every move reports a wall
+/// let interpreter = Interpreter::new(vec![3, 9, 104, 0, 1105, 1, 0, 99, 0, 0]);
+/// let map = robot::explore(&interpreter, &Droid, true, Search::BreadthFirst).unwrap();
+/// assert_eq!(map.len(), 5);
+/// assert_eq!(map.iter().filter(|&(_, &open)| open).count(), 1);
+/// ```
+pub mod robot {
+    use super::adapters::{AdapterError, Grid, Tuples};
+    use super::{ErrorState, Interpreter, State};
+    use std::collections::VecDeque;
+
+    /// A position on a grid, with `y` increasing downwards. Ordered by row, then column.
+    #[derive(Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+    pub struct Location {
+        pub y: i64,
+        pub x: i64,
+    }
+
+    impl Location {
+        pub const fn new(x: i64, y: i64) -> Self {
+            Self { y, x }
+        }
+
+        /// The location one space away in `dir`
+        pub const fn step(self, dir: Direction) -> Self {
+            match dir {
+                Direction::Up => Self::new(self.x, self.y - 1),
+                Direction::Right => Self::new(self.x + 1, self.y),
+                Direction::Down => Self::new(self.x, self.y + 1),
+                Direction::Left => Self::new(self.x - 1, self.y),
+            }
+        }
+
+        /// The four adjacent locations, along with the direction of each
+        pub fn neighbors(self) -> [(Self, Direction); 4] {
+            Direction::ALL.map(|dir| (self.step(dir), dir))
+        }
+
+        /// Look up the location in `grid`
+        pub fn get<T>(self, grid: &Grid<T>) -> Option<&T> {
+            grid.get(self.x, self.y)
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+    pub enum Direction {
+        Up,
+        Right,
+        Down,
+        Left,
+    }
+
+    impl Direction {
+        /// Every direction, clockwise from `Up`
+        pub const ALL: [Self; 4] = [Self::Up, Self::Right, Self::Down, Self::Left];
+
+        pub const fn turn_right(self) -> Self {
+            match self {
+                Self::Up => Self::Right,
+                Self::Right => Self::Down,
+                Self::Down => Self::Left,
+                Self::Left => Self::Up,
+            }
+        }
+
+        pub const fn turn_left(self) -> Self {
+            self.turn_right().turn_right().turn_right()
+        }
+
+        pub const fn reverse(self) -> Self {
+            self.turn_right().turn_right()
+        }
+
+        pub const fn turn(self, turn: Turn) -> Self {
+            match turn {
+                Turn::Left => self.turn_left(),
+                Turn::Right => self.turn_right(),
+            }
+        }
+
+        /// Parse the arrow used to draw a robot facing this direction, as in `^`, `>`, `v` or `<`
+        pub const fn from_arrow(arrow: u8) -> Option<Self> {
+            match arrow {
+                b'^' => Some(Self::Up),
+                b'>' => Some(Self::Right),
+                b'v' => Some(Self::Down),
+                b'<' => Some(Self::Left),
+                _ => None,
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum Turn {
+        Left,
+        Right,
+    }
+
+    #[derive(Debug)]
+    pub enum RobotError {
+        Intcode(ErrorState),
+        Adapter(AdapterError),
+        /// The program responded with values that the robot's protocol doesn't understand
+        InvalidResponse(Vec<i64>),
+    }
+
+    impl From<ErrorState> for RobotError {
+        fn from(err: ErrorState) -> Self {
+            Self::Intcode(err)
+        }
+    }
+
+    impl From<AdapterError> for RobotError {
+        fn from(err: AdapterError) -> Self {
+            Self::Adapter(err)
+        }
+    }
+
+    /// How to talk to a robot that's steered by the caller: it's sent a command to move one
+    /// space, and responds with a status code describing the tile it tried to move onto
+    pub trait Protocol {
+        type Tile: Copy;
+        /// Encode a command to move in `dir`
+        fn command(&self, dir: Direction) -> i64;
+        /// Decode the status code that the robot responded with, or `None` if it's invalid
+        fn status(&self, value: i64) -> Option<Self::Tile>;
+        /// Whether the robot moved onto the tile, rather than being blocked by it
+        fn is_passable(&self, tile: Self::Tile) -> bool;
+    }
+
+    /// The order in which [`explore`] visits locations
+    #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+    pub enum Search {
+        BreadthFirst,
+        DepthFirst,
+    }
+
+    /// Map out every tile reachable by a robot controlled by `interpreter`, which starts at
+    /// `(0, 0)` on the `start` tile.
+    ///
+    /// Rather than moving the robot back and forth, each move is tried out on a clone of the
+    /// interpreter that was already at the neighboring location, so backtracking is free. A robot
+    /// that halts after moving is treated as unable to go any further.
+    pub fn explore<P: Protocol>(
+        interpreter: &Interpreter<'_>,
+        protocol: &P,
+        start: P::Tile,
+        search: Search,
+    ) -> Result<Grid<P::Tile>, RobotError> {
+        let mut map = Grid::default();
+        let origin = Location::default();
+        map.set(origin.x, origin.y, start);
+        let mut frontier = VecDeque::from([(origin, interpreter.clone())]);
+
+        loop {
+            let next = match search {
+                Search::BreadthFirst => frontier.pop_front(),
+                Search::DepthFirst => frontier.pop_back(),
+            };
+            let Some((loc, interpreter)) = next else {
+                return Ok(map);
+            };
+            for (neighbor, dir) in loc.neighbors() {
+                if neighbor.get(&map).is_some() {
+                    continue;
+                }
+                let mut robot = interpreter.clone();
+                let (output, state) = robot.run_through_inputs([protocol.command(dir)])?;
+                let &[value] = output.as_slice() else {
+                    return Err(RobotError::InvalidResponse(output));
+                };
+                let tile = protocol
+                    .status(value)
+                    .ok_or(RobotError::InvalidResponse(output))?;
+                map.set(neighbor.x, neighbor.y, tile);
+                if protocol.is_passable(tile) && state == State::Awaiting {
+                    frontier.push_back((neighbor, robot));
+                }
+            }
+        }
+    }
+
+    /// How to talk to a robot that steers itself: it's sent a reading of the tile that it's on,
+    /// and responds with the tile to leave in its place and which way to turn, then moves forward
+    /// one space
+    pub trait Turtle {
+        type Tile;
+        /// Encode a reading of the robot's current tile, which is `None` if it's not on the map
+        fn sense(&self, tile: Option<&Self::Tile>) -> i64;
+        /// Decode the robot's response, or return `None` if it's invalid
+        fn act(&self, response: [i64; 2]) -> Option<(Self::Tile, Turn)>;
+    }
+
+    /// Run a robot controlled by `interpreter` until it halts, starting at `loc` facing `facing`,
+    /// and recording the tiles it leaves behind in `map`. Returns the robot's final location.
+    pub fn drive<T: Turtle>(
+        interpreter: &mut Interpreter<'_>,
+        turtle: &T,
+        map: &mut Grid<T::Tile>,
+        mut loc: Location,
+        mut facing: Direction,
+    ) -> Result<Location, RobotError> {
+        let mut tuples = Tuples::<2>::new(interpreter);
+        let mut state = State::Awaiting;
+        loop {
+            // if paused by a breakpoint, resume without sending another reading
+            if state == State::Awaiting {
+                tuples.send([turtle.sense(loc.get(map))]);
+            }
+            let responses;
+            (responses, state) = tuples.run()?;
+            for response in responses {
+                let (tile, turn) = turtle
+                    .act(response)
+                    .ok_or_else(|| RobotError::InvalidResponse(response.to_vec()))?;
+                map.set(loc.x, loc.y, tile);
+                facing = facing.turn(turn);
+                loc = loc.step(facing);
+            }
+            if state == State::Halted {
+                return Ok(loc);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    /// Example program from day 9, which takes no input and outputs its own code
+    #[test]
+    fn quine() {
+        let quine_code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut interpreter = Interpreter::new(quine_code.clone());
+        let (outputs, State::Halted) = interpreter.run_through_inputs(Vec::new()).unwrap() else {
+            panic!()
+        };
+        assert_eq!(quine_code, outputs);
+    }
+
+    /// Example program from day 9, which "should output a 16-digit number"
+    #[test]
+    fn output_sixteen_digit() {
+        let mut interpreter = Interpreter::new([1102, 34915192, 34915192, 7, 4, 7, 99, 0]);
+        let (outputs, State::Halted) = interpreter.run_through_inputs(Vec::new()).unwrap() else {
+            panic!()
+        };
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].to_string().len(), 16);
+    }
+
+    /// Example program from day 9, which "should output the large number in the middle"
+    #[test]
+    fn large_number() {
+        let mut interpreter = Interpreter::new([104, 1125899906842624, 99]);
+        let (outputs, State::Halted) = interpreter.run_through_inputs(Vec::new()).unwrap() else {
+            panic!()
+        };
+        assert_eq!(outputs, vec![1125899906842624]);
+    }
+
+    /// Ensure that failure due to missing input leaves the interpreter in a sane state that can
+    /// be recovered from
+    #[test]
+    fn missing_input_recoverable() {
+        let mut interpreter = Interpreter::new(vec![3, 10, 4, 10, 99]);
+        let old_state = interpreter.clone();
+
+        let failed_run = interpreter.run_through_inputs(Vec::new());
+
+        // make sure that the failure returned the right ErrorState and left both `outputs` and
+        // `interpreter` unchanged
+        assert_eq!(failed_run, Ok((vec![], State::Awaiting)));
+        assert_eq!(interpreter, old_state);
+
+        // make sure that interpreter can still be used
+        assert_eq!(
+            interpreter.run_through_inputs(vec![1].into_iter()),
+            Ok((vec![1], State::Halted))
+        );
+    }
+
+    /// Make sure that memory equality depends on populated pages, and only populated pages
+    #[test]
+    fn memory_equality() {
+        let mem: IntcodeMem = [1, 2, 3].into_iter().collect();
+        let mut other = mem.clone();
+        assert_eq!(mem, other);
+        other[1] = 5;
+        assert_ne!(mem, other);
+        other[1] = 2;
+        // a page that's written to, but only holds zeros, is the same as one never written to
+        other[1 << 40] = 0;
+        assert_eq!(mem, other);
+        assert_eq!(format!("{mem:?}"), format!("{other:?}"));
+        other[1 << 40] = 1;
+        assert_ne!(mem, other);
+
+        // blank pages within the initial code are also ignored
+        let padded: IntcodeMem = [1, 2, 3].into_iter().chain([0; 2048]).collect();
+        assert_eq!(mem, padded);
+        assert_eq!(padded.page_count(), 1);
+        assert!(
+            padded
+                .clone()
+                .into_iter()
+                .eq([1, 2, 3].into_iter().chain([0; 509]))
+        );
+
+        let mut interpreter = Interpreter::new(vec![3, 10, 4, 10, 99]);
+        let other = Interpreter::new(vec![3, 11, 4, 11, 99]);
+        assert_ne!(interpreter, other);
+        interpreter.mem_override(1, 11);
+        interpreter.mem_override(3, 11);
+        assert_eq!(interpreter, other);
+        interpreter.mem_override(600, 1);
+        assert_ne!(interpreter, other);
+        interpreter.mem_override(600, 0);
+        assert_eq!(interpreter, other);
+    }
+
+    /// Sparse writes to high addresses should only allocate and iterate over the pages written to
+    #[test]
+    fn sparse_memory() {
+        // Writes 7 to address 100_000, and 0 to address 10_000_000
+        let mut interpreter =
+            Interpreter::new(vec![1101, 3, 4, 100_000, 1101, 0, 0, 10_000_000, 99]);
+        assert_eq!(interpreter.dirty_pages().count(), 0);
+        interpreter.run_through_inputs(Vec::new()).unwrap();
+        assert_eq!(interpreter.mem_get(100_000), 7);
+        assert_eq!(interpreter.mem_get(10_000_000), 0);
+        assert_eq!(
+            interpreter.dirty_pages().collect::<Vec<_>>(),
+            [100_000 & !511, 10_000_000 & !511]
+        );
+        assert_eq!(interpreter.code.page_count(), 3);
+
+        let cloned = interpreter.clone();
+        assert_eq!(cloned, interpreter);
+        assert_eq!(cloned.code.page_count(), 3);
+        let memory: Vec<i64> = cloned.code.into_iter().collect();
+        assert_eq!(memory.len(), (100_000 & !511) + 512);
+        assert_eq!(memory[100_000], 7);
+        assert_eq!(memory.iter().filter(|&&i| i != 0).count(), 8);
+
+        interpreter.clear_dirty_pages();
+        interpreter.mem_override(100_001, 8);
+        assert_eq!(
+            interpreter.dirty_pages().collect::<Vec<_>>(),
+            [100_000 & !511]
+        );
+    }
+
+    /// Disassemble the quine, which loops back to its start until it's output all of its code
+    #[test]
+    fn disassemble_quine() {
+        let quine_code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let listing = disasm::disassemble(quine_code, [0]).to_string();
+        let lines: Vec<&str> = listing
+            .lines()
+            .map(|l| l.split(';').next().unwrap().trim())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "code_0:",
+                "rbo(1)",
+                "output(r-1)",
+                "add(p100, 1, p100)",
+                "eq(p100, 16, p101)",
+                "jz(p101, code_0)",
+                "halt",
+            ]
+        );
+    }
+
+    /// Make sure that code and data are told apart, that unconditional jumps don't fall through,
+    /// and that jumps into the middle of an instruction aren't given labels
+    #[test]
+    fn disassembly_separates_data() {
+        let code = vec![1101, 0, 0, 12, 1005, 12, 11, 4, 13, 1106, 0, 7, 0, 42, 7];
+        let disassembly = disasm::disassemble(code, [0]);
+        assert!((0..12).all(|addr| disassembly.is_code(addr)));
+        assert!((12..15).all(|addr| !disassembly.is_code(addr)));
+        assert_eq!(disassembly.get(13), Some(disasm::Item::Data(42)));
+        assert_eq!(disassembly.label(7).as_deref(), Some("code_7"));
+        assert_eq!(disassembly.label(11), None);
+        assert_eq!(disassembly.label(12).as_deref(), Some("data_12"));
+        assert!(disassembly.to_string().contains("jnz(pdata_12, 11)"));
+    }
+
+    /// Make sure that unpopulated memory between the program and a far-off write isn't listed
+    #[test]
+    fn disassembly_skips_sparse_memory() {
+        let mut interpreter = Interpreter::new(vec![1101, 0, 0, 12, 99]);
+        interpreter.mem_override(1 << 40, 1);
+        interpreter.mem_override((1 << 40) + 600, 2);
+        let disassembly = interpreter.disassemble([]);
+        let addrs: Vec<u64> = disassembly.items().map(|(addr, _)| addr).collect();
+        assert_eq!(addrs[..2], [0, 4]);
+        assert_eq!(
+            addrs[2..],
+            ((1 << 40)..=(1 << 40) + 600).collect::<Vec<_>>()
+        );
+        assert_eq!(disassembly.get(1 << 40), Some(disasm::Item::Data(1)));
+        assert_eq!(interpreter.cfg([]).blocks().count(), 1);
+    }
+
+    /// Assemble the quine from its textual form, and make sure it still works
+    #[test]
+    fn assemble_quine() {
+        let quine_code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let src = "
+            start:
+                rbo(1)
+                output(r-1)          ; output the next value
+                add(p100, 1, p100)
+                eq(p100, 16, p101)
+                jz(p101, start)
+                halt
+        ";
+        let assembled = asm::assemble(src).unwrap();
+        assert_eq!(assembled, quine_code);
+        let mut interpreter = Interpreter::new(assembled);
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((quine_code, State::Halted))
+        );
+    }
+
+    /// Make sure that disassembly listings can be assembled back into the original program
+    #[test]
+    fn disassembly_round_trip() {
+        let code = vec![1101, 0, 0, 12, 1005, 12, 11, 4, 13, 1106, 0, 7, 0, 42, 7];
+        let listing = disasm::disassemble(code.clone(), [0]).to_string();
+        assert_eq!(asm::assemble(&listing), Ok(code));
+    }
+
+    #[test]
+    fn assembler_errors() {
+        use asm::{AsmError, AsmErrorKind};
+        assert_eq!(
+            asm::assemble("halt\n  add(1, 2, 3)"),
+            Err(AsmError {
+                line: 2,
+                column: 13,
+                kind: AsmErrorKind::WriteToImmediate
+            })
+        );
+        assert_eq!(
+            asm::assemble("x: jz(0, y)\nx: halt"),
+            Err(AsmError {
+                line: 2,
+                column: 1,
+                kind: AsmErrorKind::DuplicateLabel("x".into())
+            })
+        );
+        assert_eq!(
+            asm::assemble("jz(0, y)"),
+            Err(AsmError {
+                line: 1,
+                column: 7,
+                kind: AsmErrorKind::UndefinedLabel("y".into())
+            })
+        );
+        assert_eq!(
+            asm::assemble("  mov(1, p2)").unwrap_err().kind,
+            AsmErrorKind::UnknownMnemonic("mov".into())
+        );
+    }
+
+    /// Make sure that breakpoints pause before the instruction, and that execution can resume
+    /// without input being lost
+    #[test]
+    fn breakpoints() {
+        let mut interpreter = Interpreter::new(vec![3, 10, 4, 10, 99]);
+        interpreter.add_breakpoint(2);
+        assert_eq!(
+            interpreter.run_through_inputs([5, 6]),
+            Ok((vec![], State::Break(Breakpoint::Address(2))))
+        );
+        assert_eq!(interpreter.ip(), 2);
+        assert_eq!(interpreter.mem_get(10), 5);
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((vec![5], State::Halted))
+        );
+
+        // breakpoint on an input instruction, which then needs to wait for input
+        let mut interpreter = Interpreter::new(vec![3, 10, 4, 10, 99]);
+        interpreter.add_breakpoint(0);
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((vec![], State::Break(Breakpoint::Address(0))))
+        );
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((vec![], State::Awaiting))
+        );
+        assert_eq!(
+            interpreter.run_through_inputs([7]),
+            Ok((vec![7], State::Halted))
+        );
+    }
+
+    #[test]
+    fn watchpoints() {
+        // add(p9, p9, p10), output(p10), halt, then data
+        let code = vec![1, 9, 9, 10, 4, 10, 99, 0, 0, 21, 0];
+        let mut interpreter = Interpreter::new(code.clone());
+        interpreter.add_watchpoint(10, Watch::Write);
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((
+                vec![],
+                State::Break(Breakpoint::Write {
+                    ip: 0,
+                    addr: 10,
+                    old: 0,
+                    new: 42
+                })
+            ))
+        );
+        assert_eq!(interpreter.ip(), 4);
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((vec![42], State::Halted))
+        );
+
+        let mut interpreter = Interpreter::new(code);
+        interpreter.add_watchpoint(10, Watch::Read);
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((vec![42], State::Break(Breakpoint::Read { ip: 4, addr: 10 })))
+        );
+    }
+
+    #[test]
+    fn single_step() {
+        let mut interpreter = Interpreter::new(vec![3, 10, 4, 10, 99]);
+        let mut outputs = Vec::new();
+        assert_eq!(
+            interpreter.step(&mut None, &mut outputs),
+            Ok(Some(State::Awaiting))
+        );
+        assert_eq!(interpreter.step(&mut Some(3), &mut outputs), Ok(None));
+        assert_eq!(interpreter.ip(), 2);
+        assert_eq!(interpreter.step(&mut None, &mut outputs), Ok(None));
+        assert_eq!(outputs, vec![3]);
+        assert_eq!(
+            interpreter.step(&mut None, &mut outputs),
+            Ok(Some(State::Halted))
+        );
+    }
+
+    /// Make sure that a snapshot taken partway through execution restores to an identical
+    /// interpreter, including sparse high-address memory
+    #[test]
+    fn snapshot_round_trip() {
+        let mut interpreter = Interpreter::new(vec![3, 10, 4, 10, 99]);
+        interpreter.mem_override(1 << 40, -7);
+        let mut saved = Vec::new();
+        interpreter.save_snapshot(&mut saved).unwrap();
+        let mut restored = Interpreter::from_snapshot(&mut saved.as_slice()).unwrap();
+        assert_eq!(restored, interpreter);
+        assert_eq!(restored.mem_get(1 << 40), -7);
+        assert_eq!(
+            restored.run_through_inputs([4]),
+            Ok((vec![4], State::Halted))
+        );
+
+        saved[7] = snapshot::VERSION + 1;
+        assert!(matches!(
+            Interpreter::from_snapshot(&mut saved.as_slice()),
+            Err(snapshot::SnapshotError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            Interpreter::from_snapshot(&mut &b"not a snapshot"[..]),
+            Err(snapshot::SnapshotError::BadMagic)
+        ));
+    }
+
+    /// Make sure that stepping back restores the exact prior state, and that the journal's
+    /// memory budget is respected
+    #[test]
+    fn reverse_execution() {
+        let quine_code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut interpreter = Interpreter::new(quine_code.clone());
+        interpreter.enable_journal(1 << 20);
+        let start = interpreter.clone();
+        let mut outputs = Vec::new();
+        let mut history = vec![start.clone()];
+        for _ in 0..20 {
+            assert_eq!(interpreter.step(&mut None, &mut outputs), Ok(None));
+            history.push(interpreter.clone());
+        }
+        assert_eq!(interpreter.step_back(5), 5);
+        assert_eq!(interpreter, history[15]);
+        assert_eq!(interpreter.step_back(100), 15);
+        assert_eq!(interpreter, start);
+        assert_eq!(interpreter.mem_get(100), 0);
+
+        // run to completion, then rewind to right before the final output
+        let (final_outputs, State::Halted) =
+            interpreter.run_through_inputs(std::iter::empty()).unwrap()
+        else {
+            panic!()
+        };
+        assert_eq!(final_outputs, quine_code);
+        assert_eq!(interpreter.rewind_to_last_output(), Some(4));
+        assert_eq!(interpreter.ip(), 2);
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((vec![99], State::Halted))
+        );
+
+        // a journal with room for 3 instructions
+        let mut interpreter = Interpreter::new(quine_code);
+        interpreter.enable_journal(3 * std::mem::size_of::<JournalEntry>());
+        for _ in 0..10 {
+            interpreter.step(&mut None, &mut outputs).unwrap();
+        }
+        assert_eq!(interpreter.step_back(10), 3);
+
+        // pre-computed instructions aren't journalled, and don't leak into the next entry
+        let mut interpreter = Interpreter::new(vec![1101, 1, 1, 20, 4, 20, 99]);
+        interpreter.enable_journal(1 << 20);
+        interpreter.precompute().unwrap();
+        assert_eq!(interpreter.step(&mut None, &mut outputs), Ok(None));
+        assert_eq!(interpreter.step_back(2), 1);
+        assert_eq!((interpreter.ip(), interpreter.mem_get(20)), (4, 2));
+
+        // restoring a snapshot discards the journal, as it belongs to a different state
+        let mut interpreter = Interpreter::new(vec![1101, 4, 5, 20, 1101, 0, 0, 20, 99]);
+        interpreter.enable_journal(1 << 20);
+        interpreter.step(&mut None, &mut outputs).unwrap();
+        let mut saved = Vec::new();
+        interpreter.save_snapshot(&mut saved).unwrap();
+        interpreter.step(&mut None, &mut outputs).unwrap();
+        interpreter.restore_snapshot(&mut &saved[..]).unwrap();
+        assert_eq!(interpreter.step_back(1), 0);
+        assert_eq!((interpreter.ip(), interpreter.mem_get(20)), (4, 9));
+    }
+
+    /// Make sure that the text logger still writes the same format as it always did, and that
+    /// trace events hold resolved values
+    #[test]
+    fn tracing() {
+        let mut log = Vec::new();
+        let mut interpreter = Interpreter::new(vec![1001, 9, 5, 9, 4, 9, 99, 0, 0, 10]);
+        interpreter.log_with(&mut log);
+        interpreter.run_through_inputs(std::iter::empty()).unwrap();
+        drop(interpreter);
+        assert_eq!(
+            String::from_utf8(log).unwrap(),
+            "ip:        0 | rbo:     0 | 01001 [add(p9, 5, p9)]\n\
+             ip:        4 | rbo:     0 | 00004 [output(p9)]\n\
+             ip:        6 | rbo:     0 | 00099 [halt]\n"
+        );
+
+        struct Collector(Vec<TraceEvent>);
+        impl Tracer for Collector {
+            fn trace(&mut self, event: &TraceEvent) -> io::Result<()> {
+                self.0.push(*event);
+                Ok(())
+            }
+        }
+        let mut collector = Collector(Vec::new());
+        let mut interpreter = Interpreter::new(vec![1001, 9, 5, 9, 4, 9, 99, 0, 0, 10]);
+        interpreter.trace_with(&mut collector);
+        interpreter.run_through_inputs(std::iter::empty()).unwrap();
+        drop(interpreter);
+        assert_eq!(collector.0.len(), 3);
+        assert_eq!(collector.0[0].opcode, OpCode::Add);
+        assert_eq!(collector.0[0].values, [10, 5, 0]);
+        assert_eq!(collector.0[0].write, Some((9, 15)));
+        assert_eq!(collector.0[0].new_ip, 4);
+        assert_eq!(collector.0[1].values[0], 15);
+    }
+
+    #[test]
+    fn profiling() {
+        let quine_code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut profiler = profile::Profiler::default();
+        let mut interpreter = Interpreter::new(quine_code);
+        let disassembly = interpreter.disassemble([]);
+        interpreter.trace_with(&mut profiler);
+        interpreter.run_through_inputs(std::iter::empty()).unwrap();
+        drop(interpreter);
+
+        assert_eq!(profiler.total(), 81);
+        assert_eq!(profiler.count_at(4), 16);
+        assert_eq!(profiler.count_of(OpCode::Halt), 1);
+        assert_eq!(
+            profiler.branch_counts(12),
+            Some(profile::BranchCounts {
+                taken: 15,
+                not_taken: 1
+            })
+        );
+        assert_eq!(profiler.hot_spots()[0], (0, 16));
+        let report = profiler.report(1, Some(&disassembly));
+        assert!(report.contains("0:           16  19.75%    rbo(1)"));
+    }
+
+    /// Make sure that cached instructions are invalidated when overwritten
+    #[test]
+    fn decode_cache_self_modifying() {
+        // output(p9), overwrite the first instruction's parameter so it becomes output(p10),
+        // then jump back to it
+        let code = vec![4, 9, 1101, 0, 10, 1, 1105, 1, 0, 5, 6];
+        let mut interpreter = Interpreter::new(code.clone());
+        interpreter.enable_decode_cache();
+        let mut steps = Vec::new();
+        let mut outputs = Vec::new();
+        for _ in 0..5 {
+            interpreter.step(&mut None, &mut outputs).unwrap();
+            steps.push(interpreter.ip());
+        }
+        let mut uncached = Interpreter::new(code.clone());
+        let mut uncached_outputs = Vec::new();
+        for _ in 0..5 {
+            uncached.step(&mut None, &mut uncached_outputs).unwrap();
+        }
+        assert_eq!(steps, [2, 6, 0, 2, 6]);
+        assert_eq!(outputs, [5, 6]);
+        assert_eq!(outputs, uncached_outputs);
+        assert_eq!(interpreter, uncached);
+
+        // clones share the cache until one of them overwrites a cached instruction
+        let mut original = Interpreter::new(code);
+        original.enable_decode_cache();
+        original.step(&mut None, &mut Vec::new()).unwrap();
+        let mut fork = original.clone();
+        let cache = |i: &Interpreter| Rc::as_ptr(i.decode_cache.as_ref().unwrap());
+        assert_eq!(cache(&original), cache(&fork));
+        for _ in 0..4 {
+            fork.step(&mut None, &mut Vec::new()).unwrap();
+        }
+        assert_ne!(cache(&original), cache(&fork));
+        original.index = 0;
+        let mut outputs = Vec::new();
+        original.step(&mut None, &mut outputs).unwrap();
+        assert_eq!(outputs, [5]);
+    }
+
+    /// Benchmark the decode cache against the uncached path using the day 9 example programs.
+    ///
+    /// Run with `--ignored --nocapture` to see the timings.
+    #[test]
+    #[ignore = "benchmark"]
+    fn bench_decode_cache() {
+        use std::time::Instant;
+        const ITERATIONS: usize = 20_000;
+        let programs: [&[i64]; 3] = [
+            &[
+                109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+            ],
+            &[1102, 34915192, 34915192, 7, 4, 7, 99, 0],
+            &[104, 1125899906842624, 99],
+        ];
+        for (i, program) in programs.into_iter().enumerate() {
+            let template = Interpreter::new(program.iter().copied());
+            let mut cached_template = template.clone();
+            cached_template.enable_decode_cache();
+            let mut timings = Vec::new();
+            for template in [&template, &cached_template] {
+                let start = Instant::now();
+                for _ in 0..ITERATIONS {
+                    let mut interpreter = template.clone();
+                    let (outputs, State::Halted) =
+                        interpreter.run_through_inputs(std::iter::empty()).unwrap()
+                    else {
+                        panic!()
+                    };
+                    std::hint::black_box(outputs);
+                }
+                timings.push(start.elapsed());
+            }
+            println!(
+                "program {i}: uncached {:?}, cached {:?}",
+                timings[0], timings[1]
+            );
+        }
+    }
+
+    /// Build the control-flow graph of a program with a function call and a loop, then check
+    /// which outputs and branches depend on its input
+    #[test]
+    fn analysis() {
+        use analysis::{Exit, Taint};
+        // Squares its input three times, using a function, unless it's zero
+        let code = asm::assemble(
+            "
+                rbo(stack)
+                input(pn)
+                eq(pn, 0, pzero)
+                jnz(pzero, end)
+            again:
+                add(back, 0, r0)
+                jz(0, square)
+            back:
+                output(pn)
+                add(pcount, -1, pcount)
+                jnz(pcount, again)
+            end:
+                halt
+            square:
+                rbo(1)
+                mul(pn, pn, pn)
+                rbo(-1)
+                jz(0, r0)
+            n: data(0)
+            zero: data(0)
+            count: data(3)
+            stack: data(0)
+            ",
+        )
+        .unwrap();
+        let cfg = analysis::cfg(code.clone(), [0]);
+        let starts: Vec<u64> = cfg.blocks().map(|b| b.start).collect();
+        assert_eq!(starts, [0, 11, 18, 27, 28]);
+        assert_eq!(
+            cfg.block(11).unwrap().exit,
+            Exit::Call {
+                target: 28,
+                ret: 18
+            }
+        );
+        assert_eq!(cfg.block(28).unwrap().exit, Exit::Return);
+        let functions: Vec<(u64, Vec<u64>)> = cfg
+            .functions()
+            .map(|f| (f.entry, f.blocks.iter().copied().collect()))
+            .collect();
+        assert_eq!(functions, [(0, vec![0, 11, 18, 27]), (28, vec![28])]);
+        assert_eq!(cfg.back_edges().collect::<Vec<_>>(), [(18, 11)]);
+
+        let mut taint = Taint::default();
+        let mut interpreter = Interpreter::new(code);
+        interpreter.trace_with(&mut taint);
+        let (outputs, _) = interpreter.run_through_inputs([2]).unwrap();
+        drop(interpreter);
+        assert_eq!(outputs, [4, 16, 256]);
+        assert_eq!(taint.inputs_read(), 1);
+        assert_eq!(taint.outputs(), vec![BTreeSet::from([0]); 3]);
+        let branches: Vec<u64> = taint.input_branches().map(|(addr, _)| addr).collect();
+        assert_eq!(branches, [8]);
+        // n and zero
+        assert_eq!(taint.tainted_cells().count(), 2);
+
+        let dot = cfg.to_dot(Some(&taint));
+        assert!(dot.starts_with("digraph intcode {\n"));
+        assert!(dot.contains("subgraph cluster_28 {"));
+        assert!(dot.contains("b11 -> b28 [label=\"call\", style=dashed];"));
+        assert!(dot.contains("b18 -> b11 [label=\"taken\", style=bold];"));
+        assert!(dot.contains("color=red, tooltip=\"depends on inputs 0\""));
+    }
+
+    /// The feedback loop example from day 7, with phase settings 9,8,7,6,5
+    #[test]
+    fn network_feedback_loop() {
+        use network::{Destination, Network, NetworkState, Protocol, Scheduling};
+        struct Amplifiers {
+            thruster_signal: Option<i64>,
+        }
+        impl Protocol for Amplifiers {
+            type Message = i64;
+            const FRAME_LEN: usize = 1;
+            fn decode(&mut self, from: usize, frame: &[i64]) -> Option<(Destination, i64)> {
+                if from == 4 {
+                    self.thruster_signal = Some(frame[0]);
+                }
+                Some((Destination::Node((from + 1) % 5), frame[0]))
+            }
+            fn encode(&self, message: &i64, input: &mut VecDeque<i64>) {
+                input.push_back(*message);
+            }
+        }
+
+        let code = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let amplifiers = Amplifiers {
+            thruster_signal: None,
+        };
+        let interpreters = (0..5).map(|_| Interpreter::new(code.clone()));
+        let mut network = Network::new(amplifiers, interpreters, Scheduling::EventDriven);
+        for (i, phase) in [9, 8, 7, 6, 5].into_iter().enumerate() {
+            network.push_input(i, [phase]);
+        }
+        network.send(0, &0);
+        assert_eq!(network.run(), Ok(NetworkState::Halted));
+        assert_eq!(network.protocol().thruster_signal, Some(139629729));
+
+        // without the initial signal, every amplifier waits forever
+        let interpreters = (0..5).map(|_| Interpreter::new(code.clone()));
+        let amplifiers = Amplifiers {
+            thruster_signal: None,
+        };
+        let mut network = Network::new(amplifiers, interpreters, Scheduling::RoundRobin);
+        assert_eq!(network.run(), Ok(NetworkState::Deadlocked));
+    }
+
+    /// A network of nodes which each send one packet to the next node, which sends it on
+    /// outside of the network, before they all poll for input forever
+    #[test]
+    fn network_idle_detection() {
+        use network::{Destination, Network, NetworkState, Protocol, Scheduling};
+        struct Packets;
+        impl Protocol for Packets {
+            type Message = (i64, i64);
+            const FRAME_LEN: usize = 3;
+            fn decode(&mut self, _: usize, frame: &[i64]) -> Option<(Destination, (i64, i64))> {
+                let dest = match frame[0] {
+                    255 => Destination::External,
+                    n => Destination::Node(usize::try_from(n).ok()?),
+                };
+                Some((dest, (frame[1], frame[2])))
+            }
+            fn encode(&self, &(x, y): &(i64, i64), input: &mut VecDeque<i64>) {
+                input.extend([x, y]);
+            }
+            fn idle_input(&self) -> Option<i64> {
+                Some(-1)
+            }
+        }
+
+        // read address, send (address + 1, address, 0) - wrapping around to 0 after 2 - then
+        // forward any received packets to address 255, swapping x and y
+        let code = asm::assemble(
+            "
+                input(paddr)
+                add(paddr, 1, pdest)
+                eq(pdest, 3, pwrap)
+                jz(pwrap, send)
+                add(0, 0, pdest)
+            send:
+                output(pdest)
+                output(paddr)
+                output(0)
+            again:
+                input(px)
+                eq(px, -1, pidle)
+                jnz(pidle, again)
+                input(py)
+                output(255)
+                output(py)
+                output(px)
+                jz(0, again)
+            addr: data(0)
+            dest: data(0)
+            wrap: data(0)
+            x: data(0)
+            y: data(0)
+            idle: data(0)
+            ",
+        )
+        .unwrap();
+        let interpreters = (0..3).map(|_| Interpreter::new(code.clone()));
+        let mut network = Network::new(Packets, interpreters, Scheduling::RoundRobin);
+        for i in 0..3 {
+            network.push_input(i, [i as i64]);
+        }
+        let mut received = Vec::new();
+        while network.run() == Ok(NetworkState::External) {
+            received.extend(network.take_external());
+        }
+        received.sort();
+        assert_eq!(received, [(0, 0), (0, 1), (0, 2)]);
+        assert_eq!(network.run(), Ok(NetworkState::Idle));
+    }
+
+    #[test]
+    fn resource_limits() {
+        // Writes to an address far beyond the program's own page
+        let mut interpreter = Interpreter::new(vec![1101, 1, 1, 100_000, 104, 1, 99]);
+        interpreter.limit_pages(Some(1));
+        assert_eq!(
+            interpreter.run_through_inputs(Vec::new()),
+            Err(ErrorState::MemoryLimitExceeded(100_000))
+        );
+        assert_eq!(interpreter.ip(), 0);
+        interpreter.limit_pages(Some(2));
+        assert_eq!(
+            interpreter.run_through_inputs(Vec::new()),
+            Ok((vec![1], State::Halted))
+        );
+        assert_eq!(interpreter.mem_get(100_000), 2);
+
+        // Pages that only hold zeros still count, including in clones and restored snapshots
+        let mut interpreter = Interpreter::new(vec![1101, 0, 0, 100_000, 1101, 1, 1, 200_000, 99]);
+        interpreter.limit_pages(Some(2));
+        interpreter.step(&mut None, &mut Vec::new()).unwrap();
+        interpreter.clear_dirty_pages();
+        let mut saved = Vec::new();
+        interpreter.save_snapshot(&mut saved).unwrap();
+        let mut restored = interpreter.clone();
+        restored.mem_override(300_000, 5);
+        restored.restore_snapshot(&mut saved.as_slice()).unwrap();
+        assert_eq!(restored.dirty_pages().count(), 0);
+        for mut interpreter in [interpreter.clone(), restored] {
+            assert_eq!(interpreter.code.page_count(), 2);
+            assert_eq!(
+                interpreter.run_through_inputs(Vec::new()),
+                Err(ErrorState::MemoryLimitExceeded(200_000))
+            );
+        }
+
+        // Loops forever
+        let mut interpreter = Interpreter::new(vec![1105, 1, 0]);
+        interpreter.set_instruction_budget(Some(10));
+        assert_eq!(
+            interpreter.run_through_inputs(Vec::new()),
+            Err(ErrorState::InstructionBudgetExhausted(0))
+        );
+        assert_eq!(interpreter.instruction_budget(), Some(0));
+        interpreter.set_instruction_budget(Some(1));
+        assert!(interpreter.precompute().is_err());
+        assert_eq!(interpreter.instruction_budget(), Some(0));
+    }
+
+    /// Small xorshift PRNG, so that generated programs are reproducible from a seed
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn range(&mut self, range: std::ops::Range<i64>) -> i64 {
+            range.start + (self.next() % (range.end - range.start) as u64) as i64
+        }
+
+        fn chance(&mut self, percent: u64) -> bool {
+            self.next() % 100 < percent
+        }
+    }
+
+    /// Limits used by the fuzz tests, so that generated programs can't run forever
+    const FUZZ_BUDGET: u64 = 500;
+    const FUZZ_MAX_PAGES: usize = 4;
+    /// Generated programs that write beyond this are discarded, as iterating over the memory of
+    /// an interpreter that wrote to a far-off address would take too long
+    const FUZZ_MAX_ADDR: u64 = 1 << 12;
+
+    /// Generate a random program, mostly made up of valid instructions with small parameters,
+    /// followed by some data, and the inputs to run it with
+    fn gen_program(rng: &mut Rng) -> (Vec<i64>, Vec<i64>) {
+        const OPCODES: [i64; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+        let instructions = rng.range(1..24);
+        let data_len = rng.range(0..16);
+        // estimate of the program's size, used to pick addresses that are likely to be in range
+        let size = instructions * 3 + data_len;
+        let mut code = Vec::new();
+        for _ in 0..instructions {
+            if rng.chance(3) {
+                // garbage, which is most likely an invalid instruction
+                code.push(rng.range(-200..100_200));
+                continue;
+            }
+            let opcode = OPCODES[rng.range(0..10) as usize];
+            let mut instruction = opcode;
+            let mut params = Vec::new();
+            let op = Interpreter::parse_op(opcode).unwrap().0;
+            for (i, place) in (0..op.param_count()).zip([100, 1000, 10000]) {
+                let writes = op.write_param() == Some(i);
+                let mode = if writes && !rng.chance(5) {
+                    [0, 2][rng.range(0..2) as usize]
+                } else {
+                    rng.range(0..3)
+                };
+                instruction += mode * place;
+                let jump_target = matches!(op, OpCode::Jnz | OpCode::Jz) && i == 1;
+                params.push(match mode {
+                    1 if jump_target => rng.range(0..size),
+                    1 => rng.range(-50..50),
+                    2 => rng.range(-2..16),
+                    _ => rng.range(0..size + 8),
+                });
+            }
+            code.push(instruction);
+            code.extend(params);
+        }
+        code.extend((0..data_len).map(|_| rng.range(-20..20)));
+        let inputs = (0..rng.range(0..6)).map(|_| rng.range(-100..100)).collect();
+        (code, inputs)
+    }
+
+    /// Outcome of running a program until it stops
+    #[derive(Debug, PartialEq)]
+    struct Outcome {
+        outputs: Vec<i64>,
+        memory: Vec<i64>,
+        result: Result<State, ErrorState>,
+    }
+
+    /// A deliberately simple evaluator to check the interpreter against, using a flat `Vec` for
+    /// memory, and the same limits as the fuzz tests.
+    ///
+    /// Methods return `Ok(None)` if an arithmetic operation overflows, or memory beyond
+    /// [`FUZZ_MAX_ADDR`] is written to, in which case the program should be discarded.
+    struct Reference {
+        mem: Vec<i64>,
+        pages: BTreeSet<u64>,
+        ip: u64,
+        rbo: i64,
+        modes: [i64; 3],
+    }
+
+    impl Reference {
+        fn read(&self, addr: u64) -> i64 {
+            self.mem.get(addr as usize).copied().unwrap_or(0)
+        }
+
+        fn addr(&self, n: u64) -> Result<Option<u64>, ErrorState> {
+            let raw = self.read(self.ip + n + 1);
+            let Some(addr) = (match self.modes[n as usize] {
+                0 => Some(raw),
+                1 => return Err(ErrorState::WriteToImmediate(raw)),
+                _ => raw.checked_add(self.rbo),
+            }) else {
+                return Ok(None);
+            };
+            Ok(Some(u64::try_from(addr)?))
+        }
+
+        fn val(&self, n: u64) -> Result<Option<i64>, ErrorState> {
+            if self.modes[n as usize] == 1 {
+                Ok(Some(self.read(self.ip + n + 1)))
+            } else {
+                Ok(self.addr(n)?.map(|addr| self.read(addr)))
+            }
+        }
+
+        fn write(&mut self, n: u64, value: i64) -> Result<Option<()>, ErrorState> {
+            let Some(dest) = self.addr(n)? else {
+                return Ok(None);
+            };
+            if !self.pages.contains(&(dest & !511)) {
+                if self.pages.len() >= FUZZ_MAX_PAGES {
+                    return Err(ErrorState::MemoryLimitExceeded(dest));
+                }
+                self.pages.insert(dest & !511);
+            }
+            if dest >= FUZZ_MAX_ADDR {
+                return Ok(None);
+            }
+            if self.mem.len() <= dest as usize {
+                self.mem.resize(dest as usize + 1, 0);
+            }
+            self.mem[dest as usize] = value;
+            Ok(Some(()))
+        }
+
+        /// Run `code` with all of `inputs`, returning `None` if the program should be discarded
+        fn eval(code: &[i64], inputs: &[i64]) -> Option<Outcome> {
+            let mut vm = Reference {
+                mem: code.to_vec(),
+                pages: (0..=code.len() as u64 / 512).map(|p| p * 512).collect(),
+                ip: 0,
+                rbo: 0,
+                modes: [0; 3],
+            };
+            let mut inputs = inputs.iter().copied();
+            let mut outputs = Vec::new();
+            let mut budget = FUZZ_BUDGET;
+            let result = 'run: loop {
+                /// Unwrap the result of a method, ending the run if it's an error
+                macro_rules! try_run {
+                    ($e: expr) => {
+                        match $e {
+                            Ok(v) => v?,
+                            Err(e) => break 'run Err(e),
+                        }
+                    };
+                }
+                if budget == 0 {
+                    break Err(ErrorState::InstructionBudgetExhausted(vm.ip));
+                }
+                let instruction = vm.read(vm.ip);
+                vm.modes = [
+                    (instruction / 100) % 10,
+                    (instruction / 1000) % 10,
+                    instruction / 10000,
+                ];
+                if let Some(&mode) = vm.modes.iter().find(|m| !(0..=2).contains(*m)) {
+                    break Err(ErrorState::UnknownMode(mode));
+                }
+                match instruction % 100 {
+                    op @ (1 | 2 | 7 | 8) => {
+                        let (a, b) = (try_run!(vm.val(0)), try_run!(vm.val(1)));
+                        let value = match op {
+                            1 => a.checked_add(b)?,
+                            2 => a.checked_mul(b)?,
+                            7 => i64::from(a < b),
+                            _ => i64::from(a == b),
+                        };
+                        try_run!(vm.write(2, value));
+                        vm.ip += 4;
+                    }
+                    3 => {
+                        let Some(input) = inputs.next() else {
+                            break Ok(State::Awaiting);
+                        };
+                        try_run!(vm.write(0, input));
+                        vm.ip += 2;
+                    }
+                    4 => {
+                        outputs.push(try_run!(vm.val(0)));
+                        vm.ip += 2;
+                    }
+                    op @ (5 | 6) => {
+                        if (try_run!(vm.val(0)) != 0) == (op == 5) {
+                            let target = try_run!(vm.val(1));
+                            match u64::try_from(target) {
+                                Ok(target) => vm.ip = target,
+                                Err(e) => break Err(e.into()),
+                            }
+                        } else {
+                            vm.ip += 3;
+                        }
+                    }
+                    9 => {
+                        vm.rbo = vm.rbo.checked_add(try_run!(vm.val(0)))?;
+                        vm.ip += 2;
+                    }
+                    99 => break Ok(State::Halted),
+                    op => break Err(ErrorState::UnrecognizedOpcode(op)),
+                }
+                budget -= 1;
+            };
+            Some(Outcome {
+                outputs,
+                memory: vm.mem,
+                result,
+            })
+        }
+    }
+
+    /// The interpreter's memory as a flat list, without trailing zeros
+    fn flat_memory(interpreter: &Interpreter) -> Vec<i64> {
+        let mut memory: Vec<i64> = interpreter.code.clone().into_iter().collect();
+        let len = memory.iter().rposition(|&i| i != 0).map_or(0, |i| i + 1);
+        memory.truncate(len);
+        memory
+    }
+
+    /// Run `code` with the interpreter one instruction at a time, so that outputs aren't lost if
+    /// an error occurs, and check that trying to run each input instruction without input leaves
+    /// the interpreter unchanged
+    fn interpreter_eval(code: &[i64], inputs: &[i64], decode_cache: bool) -> Outcome {
+        let mut interpreter = Interpreter::new(code.iter().copied());
+        interpreter.set_instruction_budget(Some(FUZZ_BUDGET));
+        interpreter.limit_pages(Some(FUZZ_MAX_PAGES));
+        if decode_cache {
+            interpreter.enable_decode_cache();
+        }
+        let snapshot = |interpreter: &Interpreter| {
+            let budget = interpreter.instruction_budget();
+            (
+                interpreter.ip(),
+                interpreter.rbo(),
+                budget,
+                flat_memory(interpreter),
+            )
+        };
+        let mut outputs = Vec::new();
+        let mut inputs = inputs.iter().copied();
+        let mut input = None;
+        let result = loop {
+            let before = (interpreter.mem_get(interpreter.ip()) % 100 == 3 && input.is_none())
+                .then(|| snapshot(&interpreter));
+            match interpreter.step(&mut input, &mut outputs) {
+                Ok(None) => (),
+                Ok(Some(State::Awaiting)) => {
+                    assert_eq!(before, Some(snapshot(&interpreter)));
+                    assert_eq!(
+                        interpreter.run_through_inputs(std::iter::empty()),
+                        Ok((Vec::new(), State::Awaiting))
+                    );
+                    assert_eq!(before, Some(snapshot(&interpreter)));
+                    input = inputs.next();
+                    if input.is_none() {
+                        break Ok(State::Awaiting);
+                    }
+                }
+                Ok(Some(state)) => break Ok(state),
+                Err(e) => break Err(e),
+            }
+        };
+        Outcome {
+            outputs,
+            memory: flat_memory(&interpreter),
+            result,
+        }
+    }
+
+    /// Run randomly generated programs through both the interpreter and the reference evaluator,
+    /// and make sure that they agree
+    #[test]
+    fn fuzz_against_reference() {
+        const CASES: u64 = 4000;
+        let mut rng = Rng(0x1c0d_e1c0_de15_f0a7);
+        let mut checked = 0;
+        for case in 0..CASES {
+            let (code, inputs) = gen_program(&mut rng);
+            let Some(mut expected) = Reference::eval(&code, &inputs) else {
+                continue;
+            };
+            let len = expected
+                .memory
+                .iter()
+                .rposition(|&i| i != 0)
+                .map_or(0, |i| i + 1);
+            expected.memory.truncate(len);
+            for decode_cache in [false, true] {
+                let actual = interpreter_eval(&code, &inputs, decode_cache);
+                assert_eq!(
+                    actual, expected,
+                    "case {case} (decode cache: {decode_cache}) disagrees for {code:?} with \
+                    inputs {inputs:?}"
+                );
+            }
+            checked += 1;
+        }
+        assert!(checked > CASES / 2, "only {checked} cases were checked");
+    }
+
+    #[test]
+    fn loading() {
+        let quine_code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let csv = "109,1,204,-1,1001,100,1,100,\n 1008, 100,16,101,1006,101,0,99\n";
+        assert_eq!(load::detect(csv.as_bytes()), load::Format::Csv);
+        assert_eq!(load::parse_csv(csv).unwrap(), quine_code);
+        assert!(matches!(
+            load::parse_csv("1,2,\n3x,4"),
+            Err(load::LoadError::InvalidValue { index: 2, text }) if text == "3x"
+        ));
+        assert!(matches!(
+            load::parse_csv("1,,2"),
+            Err(load::LoadError::InvalidValue { index: 1, text }) if text.is_empty()
+        ));
+
+        let listing = disasm::disassemble(quine_code.clone(), [0]).to_string();
+        assert_eq!(load::detect(listing.as_bytes()), load::Format::Asm);
+        let interpreter = load::from_bytes(listing.as_bytes()).unwrap();
+        assert_eq!(interpreter, Interpreter::new(quine_code.clone()));
+        assert!(matches!(
+            load::from_bytes(b"add(1, 2)"),
+            Err(load::LoadError::Asm(_))
+        ));
+
+        let mut interpreter = Interpreter::new(quine_code);
+        interpreter.run_through_inputs(Vec::new()).unwrap();
+        let mut snapshot = Vec::new();
+        interpreter.save_snapshot(&mut snapshot).unwrap();
+        assert_eq!(load::detect(&snapshot), load::Format::Snapshot);
+        let restored = load::from_reader(&snapshot[..]).unwrap();
+        assert_eq!(restored, interpreter);
+        snapshot.truncate(20);
+        assert!(matches!(
+            load::from_bytes(&snapshot),
+            Err(load::LoadError::Snapshot(_))
+        ));
+    }
+
+    #[test]
+    fn ascii_adapter() {
+        use adapters::{AdapterError, Ascii};
+        // Echoes its input, then after an empty line, outputs 999 more than the number of lines and
+        // halts
+        let code = asm::assemble(
+            "
+            again:
+                input(pc)
+                output(pc)
+                eq(pc, 10, pnl)
+                mul(pnl, pprev, pdone)
+                add(pnl, 0, pprev)
+                add(pn, pnl, pn)
+                jz(pdone, again)
+                add(pn, 999, pn)
+                output(pn)
+                halt
+            c: data(0)
+            nl: data(0)
+            prev: data(0)
+            done: data(0)
+            n: data(0)
+            ",
+        )
+        .unwrap();
+        let mut interpreter = Interpreter::new(code);
+        let mut ascii = Ascii::new(&mut interpreter);
+        let output = ascii.command("hello").unwrap();
+        assert_eq!(output.text, "hello\n");
+        assert_eq!(output.final_value, None);
+        assert_eq!(output.state, State::Awaiting);
+        assert!(matches!(
+            ascii.send_line("héllo"),
+            Err(AdapterError::NonAsciiInput('é'))
+        ));
+
+        let mut transcript = Vec::new();
+        let state = ascii.interact(&b"world\n\n"[..], &mut transcript).unwrap();
+        assert_eq!(state, State::Halted);
+        assert_eq!(transcript, b"world\n\n1002\n");
+
+        // A non-ASCII value that isn't the last output is an error
+        let mut interpreter = Interpreter::new(vec![104, 1024, 104, 10, 99]);
+        assert!(matches!(
+            Ascii::new(&mut interpreter).run(),
+            Err(AdapterError::NonAsciiOutput(1024))
+        ));
+    }
+
+    #[test]
+    fn tuple_adapters() {
+        use adapters::{AdapterError, GridPainter, Tuples};
+        // Outputs each triple of input as it's read, so it's waiting for input partway through
+        let code = asm::assemble(
+            "
+            again:
+                input(px)
+                input(py)
+                output(px)
+                output(py)
+                input(pz)
+                output(pz)
+                jz(0, again)
+            x: data(0)
+            y: data(0)
+            z: data(0)
+            ",
+        )
+        .unwrap();
+        let mut interpreter = Interpreter::new(code.clone());
+        let mut tuples = Tuples::<3>::new(&mut interpreter);
+        tuples.send([2, 3]);
+        assert_eq!(tuples.run().unwrap(), (Vec::new(), State::Awaiting));
+        tuples.send([-1, 4, 5]);
+        assert_eq!(tuples.run().unwrap(), (vec![[2, 3, -1]], State::Awaiting));
+
+        let mut interpreter = Interpreter::new(code);
+        let mut painter = GridPainter::new(&mut interpreter);
+        painter.send([0, 0, 1, 1, 1, 1, 2, 1, 2]);
+        let mut painted = Vec::new();
+        let state = painter.run_with(|x, y, value| painted.push((x, y, value)));
+        assert_eq!(state.unwrap(), State::Awaiting);
+        assert_eq!(painted, [(0, 0, 1), (1, 1, 1), (2, 1, 2)]);
+        let grid = painter.into_grid();
+        assert_eq!(grid.bounds(), Some(((0, 0), (2, 1))));
+        let rendered = grid.render(|cell| match cell {
+            Some(1) => '#',
+            Some(_) => '+',
+            None => '.',
+        });
+        assert_eq!(rendered, "#..\n.#+\n");
+
+        // Halting partway through a tuple is an error
+        let mut interpreter = Interpreter::new(vec![104, 1, 104, 2, 99]);
+        assert!(matches!(
+            Tuples::<3>::new(&mut interpreter).run(),
+            Err(AdapterError::IncompleteTuple(v)) if v == [1, 2]
+        ));
+    }
+
+    #[test]
+    fn extension_opcodes() {
+        let sub = ExtOp {
+            code: 10,
+            mnemonic: "sub",
+            arity: 3,
+            write_param: Some(2),
+        };
+        let square = ExtOp {
+            code: 11,
+            mnemonic: "square",
+            arity: 1,
+            write_param: None,
+        };
+        let jgt = ExtOp {
+            code: 12,
+            mnemonic: "jgt",
+            arity: 3,
+            write_param: None,
+        };
+        let stop = ExtOp {
+            code: 13,
+            mnemonic: "stop",
+            arity: 0,
+            write_param: None,
+        };
+        let code = asm::assemble_with(
+            "
+                sub(10, 3, px)
+                square(px)
+                jgt(px, 5, done)
+                halt
+            done:
+                square(-2)
+                stop
+            x: data(0)
+            ",
+            &[sub, square, jgt, stop],
+        )
+        .unwrap();
+        assert_eq!(asm::assemble("square(1)").unwrap_err().line, 1);
+
+        let mut log = Vec::new();
+        let mut interpreter = Interpreter::new(code.clone());
+        interpreter.log_with(&mut log);
+        interpreter
+            .register_opcode(sub, |args| Ok(Effect::Write(args[0] - args[1])))
+            .unwrap();
+        interpreter
+            .register_opcode(square, |args| Ok(Effect::Output(args[0] * args[0])))
+            .unwrap();
+        interpreter
+            .register_opcode(jgt, |args| match args[0] > args[1] {
+                true => Ok(Effect::Jump(args[2] as u64)),
+                false => Ok(Effect::Continue),
+            })
+            .unwrap();
+        interpreter
+            .register_opcode(stop, |_| Ok(Effect::Halt))
+            .unwrap();
+        // the target of jgt can't be known by the disassembler, so has to be given
+        let listing = interpreter.disassemble([11]).to_string();
+        assert!(listing.contains("sub(10, 3, p14)"), "{listing}");
+        assert!(listing.contains("stop"), "{listing}");
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Ok((vec![49, 4], State::Halted))
+        );
+        drop(interpreter);
+        let log = String::from_utf8(log).unwrap();
+        assert!(log.contains("01110 [sub(10, 3, p"), "{log}");
+        assert!(log.ends_with("00013 [stop]\n"), "{log}");
+
+        // Without the registration, the opcodes are still unrecognized
+        let mut interpreter = Interpreter::new(code);
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Err(ErrorState::UnrecognizedOpcode(10))
+        );
+        interpreter
+            .register_opcode(stop, |_| Ok(Effect::Halt))
+            .unwrap();
+        assert_eq!(
+            interpreter.run_through_inputs(std::iter::empty()),
+            Err(ErrorState::UnrecognizedOpcode(10))
+        );
+
+        let invalid = |code, arity, write_param| ExtOp {
+            code,
+            mnemonic: "invalid",
+            arity,
+            write_param,
+        };
+        let mut interpreter = Interpreter::new(vec![99]);
+        let nop = |_: &[i64]| Ok(Effect::Continue);
+        assert_eq!(
+            interpreter.register_opcode(invalid(1, 0, None), nop),
+            Err(ExtError::Unavailable(1))
+        );
+        assert_eq!(
+            interpreter.register_opcode(invalid(99, 0, None), nop),
+            Err(ExtError::Unavailable(99))
+        );
+        assert_eq!(
+            interpreter.register_opcode(invalid(20, 4, None), nop),
+            Err(ExtError::InvalidArity(4))
+        );
+        assert_eq!(
+            interpreter.register_opcode(invalid(20, 2, Some(2)), nop),
+            Err(ExtError::InvalidWriteParam(2))
+        );
+        assert_eq!(interpreter.register_opcode(stop, nop), Ok(()));
+        assert_eq!(
+            interpreter.register_opcode(stop, nop),
+            Err(ExtError::Unavailable(13))
+        );
+    }
+
+    #[test]
+    fn robots() {
+        use adapters::Grid;
+        use robot::{Direction, Location, Protocol, Search, Turn, Turtle};
+
+        // A droid in a corridor from (0, 0) to (3, 0), which reports 2 when it reaches the end
+        let corridor = asm::assemble(
+            "
+            loop:
+                input(pdir)
+                eq(pdir, 2, pa)
+                eq(pdir, 4, pb)
+                mul(pb, -1, pb)
+                add(px, pa, pnx)
+                add(pnx, pb, pnx)
+                eq(pdir, 3, pa)
+                eq(pdir, 1, pb)
+                mul(pb, -1, pb)
+                add(py, pa, pny)
+                add(pny, pb, pny)
+                lt(pnx, 4, pa)
+                lt(-1, pnx, pb)
+                mul(pa, pb, pa)
+                eq(pny, 0, pb)
+                mul(pa, pb, pa)
+                jz(pa, wall)
+                add(pnx, 0, px)
+                add(pny, 0, py)
+                eq(pnx, 3, pa)
+                add(pa, 1, pa)
+                output(pa)
+                jz(0, loop)
+            wall:
+                output(0)
+                jz(0, loop)
+            dir: data(0)
+            x: data(0)
+            y: data(0)
+            nx: data(0)
+            ny: data(0)
+            a: data(0)
+            b: data(0)
+            ",
+        )
+        .unwrap();
+        struct Droid;
+        impl Protocol for Droid {
+            type Tile = i64;
+            fn command(&self, dir: Direction) -> i64 {
+                dir as i64 + 1
+            }
+            fn status(&self, value: i64) -> Option<i64> {
+                (0..=2).contains(&value).then_some(value)
+            }
+            fn is_passable(&self, tile: i64) -> bool {
+                tile != 0
+            }
+        }
+        let interpreter = Interpreter::new(corridor);
+        for search in [Search::BreadthFirst, Search::DepthFirst] {
+            let map = robot::explore(&interpreter, &Droid, 1, search).unwrap();
+            let rendered = map.render(|tile| match tile {
+                Some(0) => '#',
+                Some(1) => '.',
+                Some(_) => 'O',
+                None => ' ',
+            });
+            assert_eq!(rendered, " #### \n#...O#\n #### \n");
+        }
+
+        // A turtle that paints every tile it leaves, turning left on unpainted tiles and right on
+        // painted ones, that halts after 5 moves
+        let code = asm::assemble(
+            "
+            top:
+                input(pin)
+                output(1)
+                output(pin)
+                add(pn, 1, pn)
+                lt(pn, 5, pa)
+                jnz(pa, top)
+                halt
+            in: data(0)
+            n: data(0)
+            a: data(0)
+            ",
+        )
+        .unwrap();
+        struct Painter;
+        impl Turtle for Painter {
+            type Tile = i64;
+            fn sense(&self, tile: Option<&i64>) -> i64 {
+                tile.copied().unwrap_or_default()
+            }
+            fn act(&self, [paint, turn]: [i64; 2]) -> Option<(i64, Turn)> {
+                match turn {
+                    0 => Some((paint, Turn::Left)),
+                    1 => Some((paint, Turn::Right)),
+                    _ => None,
+                }
+            }
+        }
+        let mut interpreter = Interpreter::new(code);
+        let mut map = Grid::default();
+        let end = robot::drive(
+            &mut interpreter,
+            &Painter,
+            &mut map,
+            Location::default(),
+            Direction::Up,
+        )
+        .unwrap();
+        assert_eq!(end, Location::new(1, 0));
+        assert_eq!(map.render(|tile| tile.map_or('.', |_| '#')), "##\n##\n");
+        assert_eq!(Location::new(-1, 1).get(&map), Some(&1));
     }
 }
@@ -4,47 +4,194 @@
 
 // Solution to AoC 2019 Day 25 Part 1
 
-// The program is a classic text adventure game. Through trial-and-error, I was able to determine
-// that for my input, the following input will take the bot 1 space north of the checkpoint, with
-// every item that's possible to pick up in hand, except for the boulder, which is too heavy on its
-// own.
-
-const STARTING_INPUT: &str = r#"west
-take hypercube
-west
-take space law space brochure
-west
-north
-take shell
-west
-take mug
-south
-take festive hat
-north
-east
-south
-east
-east
-east
-east
-north
-west
-north
-take whirled peas
-west
-west
-take astronaut ice cream
-south
-"#;
+// The program is a classic text adventure game. Rather than relying on a route found by hand, the
+// ship is explored automatically: each time the droid can go somewhere new, the interpreter is
+// cloned, so every room can be visited without having to backtrack. Any item that's found is
+// tried out on a clone first, and if taking it ends the game, hangs the program, or stops the
+// droid from moving, it's left where it is.
+//
+// Once the map is known, the droid goes back and collects every safe item, heads to the security
+// checkpoint, then tries each combination of items until it's the right weight to get through.
 
 // In my cargo-based dev environment, `intcode` is a separate crate, but in the in-tree version,
 // it's not.
 #[cfg(aoc_direct)]
 mod intcode;
-use intcode::Interpreter;
+use intcode::adapters::{AdapterError, Ascii};
+use intcode::{ErrorState, Interpreter, State};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// The most instructions that a single command is allowed to take before the program is assumed
+/// to be stuck in an infinite loop
+const COMMAND_BUDGET: u64 = 10_000_000;
+
+#[derive(Debug, PartialEq)]
+enum Response {
+    /// The program is waiting for the next command after outputting the text
+    Text(String),
+    /// The program halted after outputting the text
+    Halted(String),
+    /// The program didn't finish responding within `COMMAND_BUDGET` instructions
+    Hung,
+}
+
+/// Send `command` to the game, if there is one, and run until it's ready for the next one
+fn respond(interpreter: &mut Interpreter, command: Option<&str>) -> Response {
+    interpreter.set_instruction_budget(Some(COMMAND_BUDGET));
+    let mut ascii = Ascii::new(interpreter);
+    let output = match command {
+        Some(command) => ascii.command(command),
+        None => ascii.run(),
+    };
+    match output {
+        Ok(output) if output.state == State::Halted => Response::Halted(output.text),
+        Ok(output) => Response::Text(output.text),
+        Err(AdapterError::Intcode(ErrorState::InstructionBudgetExhausted(_))) => Response::Hung,
+        Err(e) => panic!("{e:?}"),
+    }
+}
+
+fn send(interpreter: &mut Interpreter, command: &str) -> Response {
+    respond(interpreter, Some(command))
+}
+
+/// Like [`send`], but panic if the game doesn't carry on as normal
+fn send_expecting_text(interpreter: &mut Interpreter, command: &str) -> String {
+    match send(interpreter, command) {
+        Response::Text(text) => text,
+        response => panic!("unexpected response to {command:?}: {response:?}"),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Room {
+    name: String,
+    doors: Vec<String>,
+    items: Vec<String>,
+}
+
+impl Room {
+    /// Parse the last room described in `text`. A single command can result in more than one
+    /// room being described, such as when the droid is ejected from the pressure-sensitive floor
+    /// back to the security checkpoint.
+    fn parse_last(text: &str) -> Option<Self> {
+        let start = text.rfind("== ")?;
+        let mut lines = text[start..].lines();
+        let name = lines.next()?.strip_prefix("== ")?.strip_suffix(" ==")?;
+        let mut room = Room {
+            name: name.to_string(),
+            doors: Vec::new(),
+            items: Vec::new(),
+        };
+        let mut list = None;
+        for line in lines {
+            match line {
+                "Doors here lead:" => list = Some(&mut room.doors),
+                "Items here:" => list = Some(&mut room.items),
+                "" => list = None,
+                _ => {
+                    if let (Some(list), Some(entry)) = (list.as_mut(), line.strip_prefix("- ")) {
+                        list.push(entry.to_string());
+                    }
+                }
+            }
+        }
+        Some(room)
+    }
+}
+
+/// Everything learned about the ship by exploring it
+#[derive(Debug, Default)]
+struct Ship {
+    start: String,
+    /// The room reached by going through each door, keyed by room name and direction
+    doors: HashMap<(String, String), String>,
+    /// Items that are safe to pick up, along with the rooms they're in
+    items: Vec<(String, String)>,
+    /// The room with the door to the pressure-sensitive floor, and the direction of that door
+    checkpoint: Option<(String, String)>,
+}
+
+/// Check whether `item` can be taken without ending the game or leaving the droid unable to move.
+/// `interpreter` is expected to be waiting for input in `room`.
+fn is_safe(mut interpreter: Interpreter, room: &Room, item: &str) -> bool {
+    if !matches!(
+        send(&mut interpreter, &format!("take {item}")),
+        Response::Text(_)
+    ) {
+        return false;
+    }
+    match send(&mut interpreter, &room.doors[0]) {
+        Response::Text(text) => Room::parse_last(&text).is_some(),
+        _ => false,
+    }
+}
 
-fn send_input(s: &str) -> impl IntoIterator<Item = i64> {
-    s.bytes().map(i64::from)
+/// Explore every room that can be reached from the starting room, which `interpreter` is about to
+/// describe once it's run
+fn explore(mut interpreter: Interpreter) -> Ship {
+    let Response::Text(text) = respond(&mut interpreter, None) else {
+        panic!("the game didn't start normally");
+    };
+    let room = Room::parse_last(&text).expect("no starting room");
+    let mut ship = Ship {
+        start: room.name.clone(),
+        ..Ship::default()
+    };
+    let mut visited = HashSet::from([room.name.clone()]);
+    let mut queue = VecDeque::from([(interpreter, room)]);
+
+    while let Some((interpreter, room)) = queue.pop_front() {
+        for item in &room.items {
+            if is_safe(interpreter.clone(), &room, item) {
+                ship.items.push((item.clone(), room.name.clone()));
+            }
+        }
+        for dir in &room.doors {
+            let mut next = interpreter.clone();
+            let text = send_expecting_text(&mut next, dir);
+            // with no items, the droid is always too light, so gets sent back to the checkpoint
+            if text.contains("Alert!") {
+                ship.checkpoint = Some((room.name.clone(), dir.clone()));
+                continue;
+            }
+            let next_room = Room::parse_last(&text).expect("moved somewhere other than a room");
+            ship.doors
+                .insert((room.name.clone(), dir.clone()), next_room.name.clone());
+            if visited.insert(next_room.name.clone()) {
+                queue.push_back((next, next_room));
+            }
+        }
+    }
+    ship
+}
+
+/// Find the directions to take to get from room `from` to room `to`
+fn route(ship: &Ship, from: &str, to: &str) -> Vec<String> {
+    let mut came_from: HashMap<&str, (&str, &str)> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(room) = queue.pop_front() {
+        if room == to {
+            break;
+        }
+        for ((src, dir), dest) in &ship.doors {
+            if src == room && dest != from && !came_from.contains_key(dest.as_str()) {
+                came_from.insert(dest, (room, dir));
+                queue.push_back(dest);
+            }
+        }
+    }
+    let mut dirs = Vec::new();
+    let mut room = to;
+    while room != from {
+        let (prev, dir) = came_from
+            .get(room)
+            .unwrap_or_else(|| panic!("no route from {from} to {to}"));
+        dirs.push(dir.to_string());
+        room = prev;
+    }
+    dirs.reverse();
+    dirs
 }
 
 fn main() {
@@ -52,61 +199,82 @@ fn main() {
     use std::fs::read_to_string;
     let input =
         read_to_string(args().nth(1).as_deref().unwrap_or("input")).expect("Failed to read file!");
-    let mut template = Interpreter::new(input.trim().split(",").map(|i| i.parse().unwrap()));
-    let (_, state) = template
-        .run_through_inputs(send_input(STARTING_INPUT))
-        .unwrap();
-    assert_eq!(state, intcode::State::Awaiting);
-
-    // There are 7 items, so clone the interpreter, and for each possible combination of items,
-    // drop the items not included in the combinations, then, if going south doesn't result in an
-    // error message, print the numeric characters that are in the output then break out of the
-    // loop.
-
-    for i in 0..=127 {
-        let mut interpreter = template.clone();
-        macro_rules! drop_item {
-            ($shl: literal, $item: literal) => {
-                if i & (1 << $shl) == 0 {
-                    drop_item!($item);
-                }
-            };
-            ($item: literal) => {
-                let (_, intcode::State::Awaiting) = interpreter
-                    .run_through_inputs(send_input(concat!("drop ", $item, "\n")))
-                    .unwrap()
-                else {
-                    panic!("not awaiting input")
-                };
-            };
+    let mut interpreter = Interpreter::new(input.trim().split(",").map(|i| i.parse().unwrap()));
+
+    let ship = explore(interpreter.clone());
+    let (checkpoint, floor_dir) = ship.checkpoint.clone().expect("no security checkpoint");
+
+    // go round collecting every safe item, then go to the checkpoint
+    let Response::Text(_) = respond(&mut interpreter, None) else {
+        panic!("the game didn't start normally");
+    };
+    let mut location = ship.start.as_str();
+    for (item, room) in &ship.items {
+        for dir in route(&ship, location, room) {
+            send_expecting_text(&mut interpreter, &dir);
         }
-        drop_item!(0, "hypercube");
-        drop_item!(1, "space law space brochure");
-        drop_item!(2, "shell");
-        drop_item!(3, "mug");
-        drop_item!(4, "festive hat");
-        drop_item!(5, "whirled peas");
-        drop_item!(6, "astronaut ice cream");
-
-        let (output, _) = interpreter
-            .run_through_inputs(send_input("south\n"))
-            .unwrap();
-        let output = String::from_utf8(
-            output
-                .into_iter()
-                .map(|b| u8::try_from(b).unwrap())
-                .collect::<Vec<_>>(),
-        )
-        .unwrap();
-        if !output.contains("Alert! Droids on this ship are") {
-            println!(
-                "{}",
-                output
-                    .chars()
-                    .filter(|c| c.is_numeric())
-                    .collect::<String>()
-            );
-            break;
+        send_expecting_text(&mut interpreter, &format!("take {item}"));
+        location = room;
+    }
+    for dir in route(&ship, location, &checkpoint) {
+        send_expecting_text(&mut interpreter, &dir);
+    }
+
+    // for each combination of items, drop the ones not included, then try to get past the
+    // pressure-sensitive floor
+    for combination in 0_u32..1 << ship.items.len() {
+        let mut attempt = interpreter.clone();
+        for (i, (item, _)) in ship.items.iter().enumerate() {
+            if combination & (1 << i) == 0 {
+                send_expecting_text(&mut attempt, &format!("drop {item}"));
+            }
+        }
+        match send(&mut attempt, &floor_dir) {
+            Response::Text(text) if text.contains("Alert!") => continue,
+            Response::Halted(text) => {
+                let password = text
+                    .split_once("typing ")
+                    .and_then(|(_, rest)| rest.split_whitespace().next())
+                    .expect("no password in the final message");
+                println!("{password}");
+                return;
+            }
+            response => panic!("unexpected response at the checkpoint: {response:?}"),
         }
     }
+    panic!("no combination of items got past the checkpoint");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Room;
+
+    #[test]
+    fn parse_room() {
+        let text = "\n\n\n== Hull Breach ==\nYou got in through a hole in the floor here. To keep \
+                    your ship from also freezing, the hole has been sealed.\n\nDoors here lead:\n\
+                    - north\n- east\n- west\n\nItems here:\n- mug\n\nCommand?\n";
+        assert_eq!(
+            Room::parse_last(text),
+            Some(Room {
+                name: "Hull Breach".into(),
+                doors: vec!["north".into(), "east".into(), "west".into()],
+                items: vec!["mug".into()],
+            })
+        );
+    }
+
+    #[test]
+    fn parse_ejected() {
+        let text = "\n\n\n== Pressure-Sensitive Floor ==\nAnalyzing...\n\nDoors here lead:\n\
+                    - south\n\nA loud, robotic voice says \"Alert! Droids on this ship are \
+                    lighter than the detected value!\" and you are ejected back to the \
+                    checkpoint.\n\n\n\n== Security Checkpoint ==\nIn the next room, a \
+                    pressure-sensitive floor will verify your identity.\n\nDoors here lead:\n\
+                    - north\n- south\n\nCommand?\n";
+        let room = Room::parse_last(text).unwrap();
+        assert_eq!(room.name, "Security Checkpoint");
+        assert_eq!(room.doors, ["north", "south"]);
+        assert!(room.items.is_empty());
+    }
 }
@@ -0,0 +1,41 @@
+#!/bin/sh
+
+# SPDX-FileCopyrightText: 2026 Eli Array Minkoff
+#
+# SPDX-License-Identifier: 0BSD
+
+# check that in-tree copies of drop-ins match the originals, or with --sync, overwrite them
+set -e
+
+drop_ins="$(dirname "$(dirname "$(dirname "$(realpath "$0")")")")/drop-ins/rust"
+
+case "$1" in
+    '') sync=false ;;
+    --sync) sync=true ;;
+    *)
+        printf 'Usage: %s [--sync]\n' "$0" >&2
+        exit 1
+        ;;
+esac
+
+status=0
+# check <drop-in> <year>
+check() {
+    for copy in "$drop_ins/../../$2"/day*/"$1"; do
+        [ -e "$copy" ] || continue
+        cmp -s "$drop_ins/$1" "$copy" && continue
+        if "$sync"; then
+            cp "$drop_ins/$1" "$copy"
+            printf 'Updated %s\n' "$(realpath "$copy")"
+        else
+            printf '%s has drifted from drop-ins/rust/%s\n' "$(realpath "$copy")" "$1" >&2
+            status=1
+        fi
+    done
+}
+
+check intcode.rs 2019
+check assembunny.rs 2016
+check elfcode.rs 2018
+
+exit "$status"
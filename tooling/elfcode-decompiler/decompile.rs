@@ -0,0 +1,1328 @@
+// SPDX-FileCopyrightText: 2026 Eli Array Minkoff
+//
+// SPDX-License-Identifier: 0BSD
+
+//! Decompilation of ElfCode programs into structured pseudo-code, for reverse engineering
+//! programs that take too long to run, such as those in days 19 and 21
+//!
+//! First, [`lift`] turns each instruction into a statement. Reads of the register bound to the
+//! instruction pointer are replaced by the address of the instruction, and writes to it become
+//! jumps. Jumping to a register plus a constant right after setting that register to the result
+//! of a comparison is a conditional branch, as long as nothing else jumps in between. Any code
+//! that can't be reached is removed, along with any write to a register that's overwritten
+//! before being read, and labels are only kept if something still jumps to them.
+//!
+//! Then, [`decompile`] recovers loops and `if` statements by repeatedly rewriting jumps, until
+//! nothing else can be rewritten. Any jump that's left is printed as a `goto`, and any jump to an
+//! address that's only known at runtime is printed as `goto L[address]`.
+//!
+//! # Example
+//!
+//! ```text
+//! #ip 2
+//! addi 0 1 0
+//! addi 1 3 1
+//! addi 0 2 0
+//! gtri 1 9 3
+//! addr 3 2 2
+//! seti 0 0 2
+//! ```
+//!
+//! decompiles to
+//!
+//! ```text
+//! fn elfcode(mut r0: usize) -> usize {
+//!     let mut r1 = 0;
+//!     r0 += 1;
+//!     loop {
+//!         r1 += 3;
+//!         r0 += 2;
+//!         if r1 > 9 {
+//!             return r0;
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::elfcode::{NUM_REGS, Opcode, Operand, Program};
+use std::collections::BTreeSet;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum ArithOp {
+    Add,
+    Mul,
+    And,
+    Or,
+}
+
+impl ArithOp {
+    pub fn apply(self, a: usize, b: usize) -> usize {
+        match self {
+            ArithOp::Add => a.wrapping_add(b),
+            ArithOp::Mul => a.wrapping_mul(b),
+            ArithOp::And => a & b,
+            ArithOp::Or => a | b,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            ArithOp::Add => "+",
+            ArithOp::Mul => "*",
+            ArithOp::And => "&",
+            ArithOp::Or => "|",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl CmpOp {
+    pub fn apply(self, a: usize, b: usize) -> bool {
+        match self {
+            CmpOp::Eq => a == b,
+            CmpOp::Ne => a != b,
+            CmpOp::Gt => a > b,
+            CmpOp::Ge => a >= b,
+            CmpOp::Lt => a < b,
+            CmpOp::Le => a <= b,
+        }
+    }
+
+    /// The comparison that's true whenever this one is false
+    pub fn negate(self) -> Self {
+        match self {
+            CmpOp::Eq => CmpOp::Ne,
+            CmpOp::Ne => CmpOp::Eq,
+            CmpOp::Gt => CmpOp::Le,
+            CmpOp::Ge => CmpOp::Lt,
+            CmpOp::Lt => CmpOp::Ge,
+            CmpOp::Le => CmpOp::Gt,
+        }
+    }
+
+    /// The equivalent comparison with the operands swapped
+    fn mirror(self) -> Self {
+        match self {
+            CmpOp::Eq | CmpOp::Ne => self,
+            CmpOp::Gt => CmpOp::Lt,
+            CmpOp::Ge => CmpOp::Le,
+            CmpOp::Lt => CmpOp::Gt,
+            CmpOp::Le => CmpOp::Ge,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+        }
+    }
+}
+
+/// A set of registers, with bit `n` set for register `n`
+type RegSet = u8;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Expr {
+    Const(usize),
+    Reg(usize),
+    Arith(ArithOp, Box<Expr>, Box<Expr>),
+    /// A comparison, which is 1 if true and 0 if false
+    Cmp(Box<Cond>),
+}
+
+impl Expr {
+    pub fn eval(&self, regs: &[usize]) -> usize {
+        match self {
+            Expr::Const(value) => *value,
+            Expr::Reg(reg) => regs[*reg],
+            Expr::Arith(op, a, b) => op.apply(a.eval(regs), b.eval(regs)),
+            Expr::Cmp(cond) => usize::from(cond.eval(regs)),
+        }
+    }
+
+    fn regs(&self) -> RegSet {
+        match self {
+            Expr::Const(_) => 0,
+            Expr::Reg(reg) => 1 << reg,
+            Expr::Arith(_, a, b) => a.regs() | b.regs(),
+            Expr::Cmp(cond) => cond.regs(),
+        }
+    }
+
+    /// Fold constants and remove operations that don't do anything, leaving any constant
+    /// operand of an arithmetic operation on the right
+    fn simplify(self) -> Self {
+        use Expr::{Arith, Const};
+        match self {
+            Arith(op, a, b) => match (op, a.simplify(), b.simplify()) {
+                (op, Const(a), Const(b)) => Const(op.apply(a, b)),
+                (op, Const(a), b) => Arith(op, Box::new(Const(a)), Box::new(b)).swapped(),
+                (ArithOp::Add | ArithOp::Or, a, Const(0)) | (ArithOp::Mul, a, Const(1)) => a,
+                (ArithOp::Mul | ArithOp::And, _, Const(0)) => Const(0),
+                (ArithOp::Add, Arith(ArithOp::Add, a, inner), Const(b))
+                    if matches!(*inner, Const(_)) =>
+                {
+                    Arith(
+                        ArithOp::Add,
+                        a,
+                        Box::new(Const(inner.eval(&[]).wrapping_add(b))),
+                    )
+                    .simplify()
+                }
+                (op, a, b) => Arith(op, Box::new(a), Box::new(b)),
+            },
+            Expr::Cmp(cond) => match cond.simplify() {
+                Cond {
+                    op,
+                    lhs: Const(a),
+                    rhs: Const(b),
+                } => Const(usize::from(op.apply(a, b))),
+                cond => Expr::Cmp(Box::new(cond)),
+            },
+            expr => expr,
+        }
+    }
+
+    /// Swap the operands of an arithmetic operation, which are all commutative, and simplify
+    /// the result
+    fn swapped(self) -> Self {
+        match self {
+            Expr::Arith(op, a, b) if !matches!(*b, Expr::Const(_)) => {
+                Expr::Arith(op, b, a).simplify()
+            }
+            expr => expr,
+        }
+    }
+
+    /// The lowest value that the expression could have, ignoring overflow
+    fn min(&self) -> usize {
+        match self {
+            Expr::Const(value) => *value,
+            Expr::Arith(ArithOp::Add, a, b) => a.min().saturating_add(b.min()),
+            Expr::Arith(ArithOp::Or, a, b) => a.min().max(b.min()),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Cond {
+    pub op: CmpOp,
+    pub lhs: Expr,
+    pub rhs: Expr,
+}
+
+impl Cond {
+    pub fn eval(&self, regs: &[usize]) -> bool {
+        self.op.apply(self.lhs.eval(regs), self.rhs.eval(regs))
+    }
+
+    /// The condition that's true whenever this one is false
+    pub fn negate(self) -> Self {
+        Cond {
+            op: self.op.negate(),
+            ..self
+        }
+    }
+
+    /// Whether `reg` is non-zero
+    fn truthy(reg: usize) -> Self {
+        Cond {
+            op: CmpOp::Ne,
+            lhs: Expr::Reg(reg),
+            rhs: Expr::Const(0),
+        }
+    }
+
+    fn regs(&self) -> RegSet {
+        self.lhs.regs() | self.rhs.regs()
+    }
+
+    fn simplify(self) -> Self {
+        let (lhs, rhs) = (self.lhs.simplify(), self.rhs.simplify());
+        // keep constants on the right, as with arithmetic
+        if matches!(lhs, Expr::Const(_)) && !matches!(rhs, Expr::Const(_)) {
+            Cond {
+                op: self.op.mirror(),
+                lhs: rhs,
+                rhs: lhs,
+            }
+        } else {
+            Cond {
+                op: self.op,
+                lhs,
+                rhs,
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Stmt {
+    Assign(usize, Expr),
+    /// The address of an instruction that something jumps to
+    Label(usize),
+    Goto(usize),
+    /// A jump to an address that's only known at runtime, which is at least `min`
+    ComputedGoto {
+        target: Expr,
+        min: usize,
+    },
+    IfGoto(Cond, usize),
+    If(Cond, Vec<Stmt>, Vec<Stmt>),
+    /// A loop, identified by the address of the instruction it starts at
+    Loop(usize, Vec<Stmt>),
+    While(usize, Cond, Vec<Stmt>),
+    /// Break out of the loop with the given identifier
+    Break(usize),
+    /// Skip to the next iteration of the loop with the given identifier
+    Continue(usize),
+    Halt,
+}
+
+impl Stmt {
+    /// Whether execution can never continue on to the next statement
+    fn diverges(&self) -> bool {
+        match self {
+            Stmt::Goto(_)
+            | Stmt::ComputedGoto { .. }
+            | Stmt::Break(_)
+            | Stmt::Continue(_)
+            | Stmt::Halt => true,
+            Stmt::If(_, then, otherwise) => {
+                then.last().is_some_and(Stmt::diverges)
+                    && otherwise.last().is_some_and(Stmt::diverges)
+            }
+            Stmt::Loop(id, body) => !breaks(body, *id),
+            Stmt::Assign(..) | Stmt::Label(_) | Stmt::IfGoto(..) | Stmt::While(..) => false,
+        }
+    }
+
+    /// The nested blocks of statements within this one
+    fn blocks(&self) -> impl Iterator<Item = &Vec<Stmt>> {
+        let blocks: [Option<&Vec<Stmt>>; 2] = match self {
+            Stmt::If(_, then, otherwise) => [Some(then), Some(otherwise)],
+            Stmt::Loop(_, body) | Stmt::While(_, _, body) => [Some(body), None],
+            _ => [None, None],
+        };
+        blocks.into_iter().flatten()
+    }
+
+    fn blocks_mut(&mut self) -> impl Iterator<Item = &mut Vec<Stmt>> {
+        let blocks: [Option<&mut Vec<Stmt>>; 2] = match self {
+            Stmt::If(_, then, otherwise) => [Some(then), Some(otherwise)],
+            Stmt::Loop(_, body) | Stmt::While(_, _, body) => [Some(body), None],
+            _ => [None, None],
+        };
+        blocks.into_iter().flatten()
+    }
+
+    /// Whether the statement, or any statement within it, jumps to `label`
+    fn jumps_to(&self, label: usize) -> bool {
+        match self {
+            Stmt::Goto(target) | Stmt::IfGoto(_, target) => *target == label,
+            Stmt::ComputedGoto { min, .. } => *min <= label,
+            _ => self.blocks().flatten().any(|stmt| stmt.jumps_to(label)),
+        }
+    }
+}
+
+/// Whether anything in `body` breaks out of the loop `id`
+fn breaks(body: &[Stmt], id: usize) -> bool {
+    body.iter().any(|stmt| match stmt {
+        Stmt::Break(target) => *target == id,
+        _ => breaks_nested(stmt, id),
+    })
+}
+
+fn breaks_nested(stmt: &Stmt, id: usize) -> bool {
+    stmt.blocks().any(|block| breaks(block, id))
+}
+
+/// Whether any loop nested within `body` breaks out of or continues the loop `id`, meaning
+/// that the loop needs a label
+fn needs_label(body: &[Stmt], id: usize) -> bool {
+    fn refers_to(body: &[Stmt], id: usize) -> bool {
+        body.iter().any(|stmt| match stmt {
+            Stmt::Break(target) | Stmt::Continue(target) => *target == id,
+            _ => stmt.blocks().any(|block| refers_to(block, id)),
+        })
+    }
+    body.iter().any(|stmt| match stmt {
+        Stmt::Loop(_, inner) | Stmt::While(_, _, inner) => refers_to(inner, id),
+        _ => stmt.blocks().any(|block| needs_label(block, id)),
+    })
+}
+
+/// A decompiled program, as a function that takes the starting value of register 0, and
+/// returns its value once the program halts
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Function {
+    /// The register bound to the instruction pointer, which isn't a variable in the function
+    pub ip_binding: Option<usize>,
+    /// The number of instructions in the program
+    pub len: usize,
+    pub body: Vec<Stmt>,
+}
+
+/// A single instruction, once lifted
+#[derive(Debug, Clone)]
+enum Node {
+    Assign(usize, Expr),
+    Nop,
+    /// A jump, where jumping to the end of the program halts it
+    Goto(usize),
+    Branch(Cond, usize, usize),
+    Computed {
+        target: Expr,
+        min: usize,
+    },
+}
+
+impl Node {
+    fn successors(&self, addr: usize, len: usize) -> Vec<usize> {
+        match self {
+            Node::Assign(..) | Node::Nop => vec![addr + 1],
+            Node::Goto(target) => vec![*target],
+            Node::Branch(_, then, otherwise) => vec![*then, *otherwise],
+            Node::Computed { min, .. } => (*min..=len).collect(),
+        }
+    }
+
+    fn uses(&self) -> RegSet {
+        match self {
+            Node::Assign(_, expr) | Node::Computed { target: expr, .. } => expr.regs(),
+            Node::Branch(cond, ..) => cond.regs(),
+            Node::Nop | Node::Goto(_) => 0,
+        }
+    }
+}
+
+/// Translate a single instruction at `addr`
+fn lift_instruction(program: &Program, addr: usize) -> Node {
+    let len = program.code.len();
+    let instruction = program.code[addr];
+    let [a, b] = [instruction.a, instruction.b]
+        .into_iter()
+        .zip(instruction.opcode.operands())
+        .map(|(operand, kind)| match kind {
+            Operand::Reg if Some(operand) == program.ip_binding => Expr::Const(addr),
+            Operand::Reg => Expr::Reg(operand),
+            Operand::Imm | Operand::Unused => Expr::Const(operand),
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("there are always two operands"));
+    let arith = |op| Expr::Arith(op, Box::new(a.clone()), Box::new(b.clone()));
+    let cmp = |op| {
+        Expr::Cmp(Box::new(Cond {
+            op,
+            lhs: a.clone(),
+            rhs: b.clone(),
+        }))
+    };
+    use Opcode as O;
+    let value = match instruction.opcode {
+        O::Addr | O::Addi => arith(ArithOp::Add),
+        O::Mulr | O::Muli => arith(ArithOp::Mul),
+        O::Banr | O::Bani => arith(ArithOp::And),
+        O::Borr | O::Bori => arith(ArithOp::Or),
+        O::Setr | O::Seti => a.clone(),
+        O::Gtir | O::Gtri | O::Gtrr => cmp(CmpOp::Gt),
+        O::Eqir | O::Eqri | O::Eqrr => cmp(CmpOp::Eq),
+    }
+    .simplify();
+    if Some(instruction.c) != program.ip_binding {
+        return Node::Assign(instruction.c, value);
+    }
+    // the instruction pointer is incremented after the write
+    match value {
+        Expr::Const(value) => Node::Goto(value.saturating_add(1).min(len)),
+        value => {
+            let target =
+                Expr::Arith(ArithOp::Add, Box::new(value), Box::new(Expr::Const(1))).simplify();
+            Node::Computed {
+                min: target.min().min(len),
+                target,
+            }
+        }
+    }
+}
+
+/// If `node` is a jump to `reg + offset`, where `reg` was just set to the result of a
+/// comparison by `prev`, it's a branch to `offset + 1` if the comparison was true, or
+/// `offset` otherwise
+fn as_branch(prev: &Node, node: &Node, len: usize) -> Option<Node> {
+    let Node::Computed {
+        target: Expr::Arith(ArithOp::Add, reg, offset),
+        ..
+    } = node
+    else {
+        return None;
+    };
+    let (Expr::Reg(reg), Expr::Const(offset)) = (&**reg, &**offset) else {
+        return None;
+    };
+    match prev {
+        Node::Assign(dest, Expr::Cmp(_)) if dest == reg => Some(Node::Branch(
+            Cond::truthy(*reg),
+            offset.saturating_add(1).min(len),
+            (*offset).min(len),
+        )),
+        _ => None,
+    }
+}
+
+/// The registers that are live at the start of each instruction, with the end of the
+/// program, where only register 0 is live, at index `len`
+fn liveness(nodes: &[Node], ip_binding: Option<usize>) -> Vec<RegSet> {
+    let len = nodes.len();
+    let mut live = vec![0; len + 1];
+    if ip_binding != Some(0) {
+        live[len] = 1;
+    }
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (addr, node) in nodes.iter().enumerate().rev() {
+            let out = live_out(node, addr, &live);
+            let defs = match node {
+                Node::Assign(reg, _) => 1 << reg,
+                _ => 0,
+            };
+            let live_in = node.uses() | (out & !defs);
+            if live_in != live[addr] {
+                live[addr] = live_in;
+                changed = true;
+            }
+        }
+    }
+    live
+}
+
+fn live_out(node: &Node, addr: usize, live: &[RegSet]) -> RegSet {
+    let len = live.len() - 1;
+    node.successors(addr, len)
+        .into_iter()
+        .fold(0, |out, succ| out | live[succ])
+}
+
+/// Translate the program into a flat list of statements, with unreachable code, dead stores
+/// and unused labels removed, but without recovering any structure
+pub fn lift(program: &Program) -> Function {
+    let len = program.code.len();
+    let mut nodes: Vec<Node> = (0..len)
+        .map(|addr| lift_instruction(program, addr))
+        .collect();
+
+    // Jumps after comparisons are branches, unless something else could jump in between.
+    // Any jump that's found not to be a branch could make another one not a branch, so keep
+    // going until nothing changes.
+    let mut branches: BTreeSet<usize> = (1..len)
+        .filter(|&addr| {
+            if let Some(branch) = as_branch(&nodes[addr - 1], &nodes[addr], len) {
+                nodes[addr] = branch;
+                true
+            } else {
+                false
+            }
+        })
+        .collect();
+    loop {
+        let mut targets = BTreeSet::new();
+        for (addr, node) in nodes.iter().enumerate() {
+            if !matches!(node, Node::Assign(..) | Node::Nop) {
+                targets.extend(node.successors(addr, len));
+            }
+        }
+        let invalid: Vec<usize> = branches.intersection(&targets).copied().collect();
+        if invalid.is_empty() {
+            break;
+        }
+        for addr in invalid {
+            branches.remove(&addr);
+            nodes[addr] = lift_instruction(program, addr);
+        }
+    }
+
+    let mut reachable = vec![false; len + 1];
+    let mut stack = vec![0];
+    while let Some(addr) = stack.pop() {
+        if !std::mem::replace(&mut reachable[addr], true) && addr < len {
+            stack.extend(nodes[addr].successors(addr, len));
+        }
+    }
+    for (node, _) in nodes.iter_mut().zip(&reachable).filter(|(_, r)| !**r) {
+        *node = Node::Nop;
+    }
+
+    // fold comparisons into the branches that use them
+    let live = liveness(&nodes, program.ip_binding);
+    for &addr in &branches {
+        let (Node::Assign(reg, Expr::Cmp(cond)), Node::Branch(..)) =
+            (&nodes[addr - 1], &nodes[addr])
+        else {
+            continue;
+        };
+        let (reg, cond) = (*reg, (**cond).clone());
+        if live_out(&nodes[addr], addr, &live) & (1 << reg) == 0 {
+            // the comparison's result is only needed by the branch
+            nodes[addr - 1] = Node::Nop;
+        } else if cond.regs() & (1 << reg) != 0 {
+            // the comparison overwrites one of its own operands, which the branch needs
+            continue;
+        }
+        if let Node::Branch(branch_cond, ..) = &mut nodes[addr] {
+            *branch_cond = cond;
+        }
+    }
+
+    // remove dead stores, which can make other stores dead
+    loop {
+        let live = liveness(&nodes, program.ip_binding);
+        let dead: Vec<usize> = (0..len)
+            .filter(|&addr| match nodes[addr] {
+                Node::Assign(reg, _) => live_out(&nodes[addr], addr, &live) & (1 << reg) == 0,
+                _ => false,
+            })
+            .collect();
+        if dead.is_empty() {
+            break;
+        }
+        for addr in dead {
+            nodes[addr] = Node::Nop;
+        }
+    }
+
+    let mut body = Vec::new();
+    for (addr, node) in nodes.into_iter().enumerate() {
+        if !reachable[addr] {
+            continue;
+        }
+        body.push(Stmt::Label(addr));
+        let goto = |target| {
+            if target == len {
+                Stmt::Halt
+            } else {
+                Stmt::Goto(target)
+            }
+        };
+        match node {
+            Node::Assign(reg, expr) => body.push(Stmt::Assign(reg, expr)),
+            Node::Nop => (),
+            Node::Goto(target) => body.push(goto(target)),
+            Node::Branch(cond, then, otherwise) => {
+                body.push(if then == len {
+                    Stmt::If(cond, vec![Stmt::Halt], Vec::new())
+                } else {
+                    Stmt::IfGoto(cond, then)
+                });
+                body.push(goto(otherwise));
+            }
+            Node::Computed { target, min } => body.push(Stmt::ComputedGoto { target, min }),
+        }
+    }
+    if len > 0 && reachable[len - 1] && !body.last().is_some_and(Stmt::diverges) {
+        body.push(Stmt::Halt);
+    }
+    let mut function = Function {
+        ip_binding: program.ip_binding,
+        len,
+        body,
+    };
+    tidy(&mut function.body);
+    function
+}
+
+/// Decompile the program into structured pseudo-code
+pub fn decompile(program: &Program) -> Function {
+    let mut function = lift(program);
+    while form_loop(&mut function.body) || form_if(&mut function.body) {
+        tidy(&mut function.body);
+    }
+    function
+}
+
+/// Simplify the statements without changing their structure, until nothing else changes
+fn tidy(body: &mut Vec<Stmt>) {
+    loop {
+        let mut changed = simplify_block(body);
+
+        let before = body.len();
+        let unused: Vec<usize> = body
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Label(label) if !body.iter().any(|stmt| stmt.jumps_to(*label)) => {
+                    Some(*label)
+                }
+                _ => None,
+            })
+            .collect();
+        body.retain(|stmt| !matches!(stmt, Stmt::Label(label) if unused.contains(label)));
+        changed |= body.len() != before;
+
+        let mut i = 0;
+        while i + 1 < body.len() {
+            match (&body[i], &body[i + 1], body.get(i + 2)) {
+                // jumping to the next statement does nothing
+                (Stmt::Goto(target) | Stmt::IfGoto(_, target), Stmt::Label(label), _)
+                    if target == label =>
+                {
+                    body.remove(i);
+                    changed = true;
+                }
+                // jumping over an unconditional jump is the same as jumping if the condition
+                // isn't met
+                (Stmt::IfGoto(_, over), Stmt::Goto(target), Some(Stmt::Label(label)))
+                    if over == label =>
+                {
+                    let target = *target;
+                    let Stmt::IfGoto(cond, _) = body.remove(i) else {
+                        unreachable!()
+                    };
+                    body[i] = Stmt::IfGoto(cond.negate(), target);
+                    changed = true;
+                }
+                _ => i += 1,
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Remove unreachable statements and empty `if` statements from `block` and any blocks
+/// within it, returning whether anything changed
+fn simplify_block(block: &mut Vec<Stmt>) -> bool {
+    let mut changed = false;
+    let mut i = 0;
+    while i < block.len() {
+        for inner in block[i].blocks_mut() {
+            changed |= simplify_block(inner);
+        }
+        if let Stmt::If(cond, then, otherwise) = &mut block[i] {
+            if then.is_empty() && otherwise.is_empty() {
+                block.remove(i);
+                changed = true;
+                continue;
+            }
+            if then.is_empty() {
+                *cond = cond.clone().negate();
+                std::mem::swap(then, otherwise);
+                changed = true;
+            }
+        }
+        if block[i].diverges() {
+            let end = (i + 1..block.len())
+                .find(|&j| matches!(block[j], Stmt::Label(_)))
+                .unwrap_or(block.len());
+            if end > i + 1 {
+                block.drain(i + 1..end);
+                changed = true;
+            }
+        }
+        i += 1;
+    }
+    changed
+}
+
+/// Replace jumps to `start` or `exit` within a loop's body with `continue` or `break`
+fn convert_jumps(block: &mut [Stmt], id: usize, start: usize, exit: Option<usize>) {
+    for stmt in block {
+        let replacement = match stmt {
+            Stmt::Goto(target) | Stmt::IfGoto(_, target) if *target == start => Stmt::Continue(id),
+            Stmt::Goto(target) | Stmt::IfGoto(_, target) if Some(*target) == exit => {
+                Stmt::Break(id)
+            }
+            _ => {
+                for inner in stmt.blocks_mut() {
+                    convert_jumps(inner, id, start, exit);
+                }
+                continue;
+            }
+        };
+        *stmt = match stmt {
+            Stmt::IfGoto(cond, _) => Stmt::If(cond.clone(), vec![replacement], Vec::new()),
+            _ => replacement,
+        };
+    }
+}
+
+/// Find a label where everything that jumps to it comes after it, with no other labels in
+/// between, and turn it into a loop, returning whether one was found
+fn form_loop(body: &mut Vec<Stmt>) -> bool {
+    for start in 0..body.len() {
+        let Stmt::Label(label) = body[start] else {
+            continue;
+        };
+        let jumps: Vec<usize> = (0..body.len())
+            .filter(|&i| body[i].jumps_to(label))
+            .collect();
+        let (Some(&first), Some(&end)) = (jumps.first(), jumps.last()) else {
+            continue;
+        };
+        if first < start
+            || body[start + 1..end]
+                .iter()
+                .any(|s| matches!(s, Stmt::Label(_)))
+        {
+            continue;
+        }
+        // a computed jump could go anywhere
+        if body[start + 1..=end]
+            .iter()
+            .any(|stmt| matches!(stmt, Stmt::ComputedGoto { .. }))
+        {
+            continue;
+        }
+        let exit = match body.get(end + 1) {
+            Some(Stmt::Label(exit)) => Some(*exit),
+            _ => None,
+        };
+        let mut inner: Vec<Stmt> = body.drain(start..=end).skip(1).collect();
+        convert_jumps(&mut inner, label, label, exit);
+        if !inner.last().is_some_and(Stmt::diverges) {
+            inner.push(Stmt::Break(label));
+        }
+        body.insert(start, make_loop(label, inner));
+        return true;
+    }
+    false
+}
+
+/// Turn a loop's body into the clearest equivalent loop
+fn make_loop(id: usize, mut body: Vec<Stmt>) -> Stmt {
+    // reaching the end of the body already continues the loop
+    while body.last() == Some(&Stmt::Continue(id)) {
+        body.pop();
+    }
+    if let [.., Stmt::If(_, then, otherwise), Stmt::Break(last)] = &body[..]
+        && *last == id
+        && then[..] == [Stmt::Continue(id)]
+        && otherwise.is_empty()
+    {
+        body.pop();
+        if let Some(Stmt::If(cond, then, _)) = body.last_mut() {
+            *cond = cond.clone().negate();
+            *then = vec![Stmt::Break(id)];
+        }
+    }
+    if let Some(Stmt::If(cond, then, otherwise)) = body.first()
+        && then[..] == [Stmt::Break(id)]
+        && otherwise.is_empty()
+    {
+        let cond = cond.clone().negate();
+        body.remove(0);
+        return Stmt::While(id, cond, body);
+    }
+    Stmt::Loop(id, body)
+}
+
+/// Find a forward jump over a block with no labels in it, and turn it into an `if`
+/// statement, or find an `if` statement that ends by jumping over such a block, and turn
+/// the block into its `else` branch. Returns whether either was found.
+fn form_if(body: &mut Vec<Stmt>) -> bool {
+    let label_after = |body: &[Stmt], from: usize, label: usize| {
+        (from..body.len())
+            .find(|&i| matches!(body[i], Stmt::Label(_)))
+            .filter(|&i| body[i] == Stmt::Label(label))
+    };
+    for i in 0..body.len() {
+        match &body[i] {
+            Stmt::IfGoto(cond, target) => {
+                let Some(end) = label_after(body, i + 1, *target) else {
+                    continue;
+                };
+                let cond = cond.clone().negate();
+                let then: Vec<Stmt> = body.drain(i + 1..end).collect();
+                body[i] = Stmt::If(cond, then, Vec::new());
+                return true;
+            }
+            Stmt::If(_, then, otherwise) if otherwise.is_empty() => {
+                let Some(&Stmt::Goto(target)) = then.last() else {
+                    continue;
+                };
+                let Some(end) = label_after(body, i + 1, target) else {
+                    continue;
+                };
+                let new_otherwise: Vec<Stmt> = body.drain(i + 1..end).collect();
+                if let Stmt::If(_, then, otherwise) = &mut body[i] {
+                    then.pop();
+                    *otherwise = new_otherwise;
+                }
+                return true;
+            }
+            _ => (),
+        }
+    }
+    false
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Const(value) => write!(f, "{value}"),
+            Expr::Reg(reg) => write!(f, "r{reg}"),
+            Expr::Arith(op, a, b) => {
+                for (i, operand) in [a, b].into_iter().enumerate() {
+                    if i == 1 {
+                        write!(f, " {} ", op.symbol())?;
+                    }
+                    match &**operand {
+                        Expr::Arith(inner, ..) if inner != op => write!(f, "({operand})")?,
+                        _ => write!(f, "{operand}")?,
+                    }
+                }
+                Ok(())
+            }
+            Expr::Cmp(cond) => write!(f, "({cond}) as usize"),
+        }
+    }
+}
+
+impl fmt::Display for Cond {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.lhs, self.op.symbol(), self.rhs)
+    }
+}
+
+struct Printer<'a> {
+    function: &'a Function,
+    /// The width of the address in each label
+    width: usize,
+    /// The loops that the statement being printed is within
+    loops: Vec<usize>,
+}
+
+impl Printer<'_> {
+    fn label(&self, addr: usize) -> String {
+        format!("L{addr:0width$}", width = self.width)
+    }
+
+    fn loop_jump(&self, keyword: &str, id: usize) -> String {
+        if self.loops.last() == Some(&id) {
+            format!("{keyword};")
+        } else {
+            format!("{keyword} 'l{id:0width$};", width = self.width)
+        }
+    }
+
+    fn block(&mut self, f: &mut fmt::Formatter<'_>, block: &[Stmt], depth: usize) -> fmt::Result {
+        for stmt in block {
+            self.stmt(f, stmt, depth)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, f: &mut fmt::Formatter<'_>, stmt: &Stmt, depth: usize) -> fmt::Result {
+        let indent = "    ".repeat(depth);
+        match stmt {
+            Stmt::Assign(reg, Expr::Arith(op, a, b)) if **a == Expr::Reg(*reg) => {
+                writeln!(f, "{indent}r{reg} {}= {b};", op.symbol())
+            }
+            Stmt::Assign(reg, Expr::Arith(op, a, b)) if **b == Expr::Reg(*reg) => {
+                writeln!(f, "{indent}r{reg} {}= {a};", op.symbol())
+            }
+            Stmt::Assign(reg, expr) => writeln!(f, "{indent}r{reg} = {expr};"),
+            Stmt::Label(addr) => writeln!(f, "{}:", self.label(*addr)),
+            Stmt::Goto(addr) => writeln!(f, "{indent}goto {};", self.label(*addr)),
+            Stmt::ComputedGoto { target, .. } => writeln!(f, "{indent}goto L[{target}];"),
+            Stmt::IfGoto(cond, addr) => {
+                writeln!(f, "{indent}if {cond} {{")?;
+                writeln!(f, "{indent}    goto {};", self.label(*addr))?;
+                writeln!(f, "{indent}}}")
+            }
+            Stmt::If(cond, then, otherwise) => {
+                writeln!(f, "{indent}if {cond} {{")?;
+                self.block(f, then, depth + 1)?;
+                let mut otherwise = otherwise;
+                // print `else if` chains without nesting them
+                while let [Stmt::If(cond, then, next)] = &otherwise[..] {
+                    writeln!(f, "{indent}}} else if {cond} {{")?;
+                    self.block(f, then, depth + 1)?;
+                    otherwise = next;
+                }
+                if !otherwise.is_empty() {
+                    writeln!(f, "{indent}}} else {{")?;
+                    self.block(f, otherwise, depth + 1)?;
+                }
+                writeln!(f, "{indent}}}")
+            }
+            Stmt::Loop(id, body) | Stmt::While(id, _, body) => {
+                write!(f, "{indent}")?;
+                if needs_label(body, *id) {
+                    write!(f, "'l{id:0width$}: ", width = self.width)?;
+                }
+                match stmt {
+                    Stmt::While(_, cond, _) => writeln!(f, "while {cond} {{")?,
+                    _ => writeln!(f, "loop {{")?,
+                }
+                self.loops.push(*id);
+                self.block(f, body, depth + 1)?;
+                self.loops.pop();
+                writeln!(f, "{indent}}}")
+            }
+            Stmt::Break(id) => writeln!(f, "{indent}{}", self.loop_jump("break", *id)),
+            Stmt::Continue(id) => writeln!(f, "{indent}{}", self.loop_jump("continue", *id)),
+            Stmt::Halt if self.function.ip_binding == Some(0) => writeln!(f, "{indent}return;"),
+            Stmt::Halt => writeln!(f, "{indent}return r0;"),
+        }
+    }
+}
+
+fn used_regs(block: &[Stmt]) -> RegSet {
+    block
+        .iter()
+        .map(|stmt| {
+            let own = match stmt {
+                Stmt::Assign(reg, expr) => (1 << reg) | expr.regs(),
+                Stmt::ComputedGoto { target, .. } => target.regs(),
+                Stmt::IfGoto(cond, _) | Stmt::If(cond, ..) | Stmt::While(_, cond, _) => cond.regs(),
+                _ => 0,
+            };
+            stmt.blocks()
+                .fold(own, |regs, inner| regs | used_regs(inner))
+        })
+        .fold(0, |a, b| a | b)
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ip_binding == Some(0) {
+            writeln!(f, "fn elfcode() {{")?;
+        } else {
+            writeln!(f, "fn elfcode(mut r0: usize) -> usize {{")?;
+        }
+        let regs = used_regs(&self.body);
+        for reg in 1..NUM_REGS {
+            if regs & (1 << reg) != 0 {
+                writeln!(f, "    let mut r{reg} = 0;")?;
+            }
+        }
+        let mut printer = Printer {
+            function: self,
+            width: self.len.saturating_sub(1).to_string().len().max(2),
+            loops: Vec::new(),
+        };
+        printer.block(f, &self.body, 1)?;
+        writeln!(f, "}}")
+    }
+}
+
+/// Run a decompiled function, for checking that it does the same thing as the program it
+/// was decompiled from
+#[cfg(test)]
+fn run(function: &Function, r0: usize, fuel: &mut usize) -> Option<usize> {
+    enum Flow {
+        Next,
+        Goto(usize),
+        Break(usize),
+        Continue(usize),
+        Halt,
+    }
+
+    fn exec(block: &[Stmt], regs: &mut [usize], fuel: &mut usize, len: usize) -> Option<Flow> {
+        for stmt in block {
+            *fuel = fuel.checked_sub(1)?;
+            let flow = match stmt {
+                Stmt::Assign(reg, expr) => {
+                    regs[*reg] = expr.eval(regs);
+                    Flow::Next
+                }
+                Stmt::Label(_) => Flow::Next,
+                Stmt::Goto(addr) => Flow::Goto(*addr),
+                Stmt::ComputedGoto { target, .. } => match target.eval(regs) {
+                    addr if addr >= len => Flow::Halt,
+                    addr => Flow::Goto(addr),
+                },
+                Stmt::IfGoto(cond, addr) if cond.eval(regs) => Flow::Goto(*addr),
+                Stmt::IfGoto(..) => Flow::Next,
+                Stmt::If(cond, then, otherwise) => exec(
+                    if cond.eval(regs) { then } else { otherwise },
+                    regs,
+                    fuel,
+                    len,
+                )?,
+                Stmt::Loop(id, body) | Stmt::While(id, _, body) => loop {
+                    *fuel = fuel.checked_sub(1)?;
+                    if let Stmt::While(_, cond, _) = stmt
+                        && !cond.eval(regs)
+                    {
+                        break Flow::Next;
+                    }
+                    match exec(body, regs, fuel, len)? {
+                        Flow::Next => (),
+                        Flow::Continue(target) if target == *id => (),
+                        Flow::Break(target) if target == *id => break Flow::Next,
+                        flow => break flow,
+                    }
+                },
+                Stmt::Break(id) => Flow::Break(*id),
+                Stmt::Continue(id) => Flow::Continue(*id),
+                Stmt::Halt => Flow::Halt,
+            };
+            if !matches!(flow, Flow::Next) {
+                return Some(flow);
+            }
+        }
+        Some(Flow::Next)
+    }
+
+    let mut regs = [0; NUM_REGS];
+    regs[0] = r0;
+    let mut start = 0;
+    loop {
+        match exec(&function.body[start..], &mut regs, fuel, function.len)? {
+            Flow::Next | Flow::Halt => return Some(regs[0]),
+            Flow::Goto(addr) => {
+                start = function
+                    .body
+                    .iter()
+                    .position(|stmt| *stmt == Stmt::Label(addr))
+                    .unwrap_or_else(|| panic!("jumped to missing label {addr}"));
+            }
+            Flow::Break(_) | Flow::Continue(_) => panic!("break or continue outside a loop"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elfcode::{Instruction, Vm, parse};
+    use std::ops::ControlFlow;
+
+    #[test]
+    fn example() {
+        let program =
+            parse("#ip 2\naddi 0 1 0\naddi 1 3 1\naddi 0 2 0\ngtri 1 9 3\naddr 3 2 2\nseti 0 0 2")
+                .unwrap();
+        assert_eq!(
+            decompile(&program).to_string(),
+            "fn elfcode(mut r0: usize) -> usize {
+    let mut r1 = 0;
+    r0 += 1;
+    loop {
+        r1 += 3;
+        r0 += 2;
+        if r1 > 9 {
+            return r0;
+        }
+    }
+}
+"
+        );
+    }
+
+    /// A program with the same structure as day 21's input
+    const DAY21_SHAPE: &str = "#ip 4\nseti 123 0 3\nbani 3 456 3\neqri 3 72 3\naddr 3 4 4\n\
+                               seti 0 0 4\nseti 0 2 3\nbori 3 65536 2\nseti 4843319 1 3\n\
+                               bani 2 255 5\naddr 3 5 3\nbani 3 16777215 3\nmuli 3 65899 3\n\
+                               bani 3 16777215 3\ngtir 256 2 5\naddr 5 4 4\naddi 4 1 4\n\
+                               seti 27 4 4\nseti 0 7 5\naddi 5 1 1\nmuli 1 256 1\ngtrr 1 2 1\n\
+                               addr 1 4 4\naddi 4 1 4\nseti 25 1 4\naddi 5 1 5\nseti 17 8 4\n\
+                               setr 5 2 2\nseti 7 9 4\neqrr 3 0 5\naddr 5 4 4\nseti 5 4 4";
+
+    #[test]
+    fn decompile_day21_shape() {
+        let program = parse(DAY21_SHAPE).unwrap();
+        let function = decompile(&program);
+        assert_eq!(
+            function.to_string(),
+            "fn elfcode(mut r0: usize) -> usize {
+    let mut r1 = 0;
+    let mut r2 = 0;
+    let mut r3 = 0;
+    let mut r5 = 0;
+    r3 = 123;
+    loop {
+        r3 &= 456;
+        r3 = (r3 == 72) as usize;
+        if r3 != 0 {
+            break;
+        }
+    }
+    r3 = 0;
+    loop {
+        r2 = r3 | 65536;
+        r3 = 4843319;
+        loop {
+            r5 = r2 & 255;
+            r3 += r5;
+            r3 &= 16777215;
+            r3 *= 65899;
+            r3 &= 16777215;
+            if r2 < 256 {
+                break;
+            }
+            r5 = 0;
+            loop {
+                r1 = r5 + 1;
+                r1 *= 256;
+                if r1 > r2 {
+                    break;
+                }
+                r5 += 1;
+            }
+            r2 = r5;
+        }
+        if r3 == r0 {
+            return r0;
+        }
+    }
+}
+"
+        );
+
+        // without structuring, the jumps are still resolved, and only the labels that are
+        // jumped to are left
+        let flat = lift(&program).to_string();
+        let labels: Vec<&str> = flat.lines().filter(|line| line.ends_with(':')).collect();
+        assert_eq!(labels, ["L01:", "L06:", "L08:", "L18:", "L26:", "L28:"]);
+        assert!(!flat.contains("L["));
+
+        // the value that makes it halt straight away is the first one it compares against
+        let mut vm = Vm::new(program);
+        let target = vm.run_with(|vm, instruction| match instruction.opcode {
+            Opcode::Eqrr => ControlFlow::Break(vm.regs[3]),
+            _ => ControlFlow::Continue(()),
+        });
+        let target = target.unwrap();
+        let mut fuel = 1_000_000;
+        assert_eq!(run(&function, target, &mut fuel), Some(target));
+    }
+
+    #[test]
+    fn decompile_computed_jumps() {
+        // `r1` isn't set by a comparison, so the jump could go anywhere from 2 onwards
+        let program =
+            parse("#ip 5\nseti 1 0 1\naddr 5 1 5\naddi 0 1 0\naddi 0 10 0\nseti 8 0 5").unwrap();
+        let function = decompile(&program);
+        assert_eq!(
+            function.to_string(),
+            "fn elfcode(mut r0: usize) -> usize {
+    let mut r1 = 0;
+    r1 = 1;
+    goto L[r1 + 2];
+L02:
+    r0 += 1;
+L03:
+    r0 += 10;
+L04:
+    return r0;
+}
+"
+        );
+        let mut fuel = 100;
+        assert_eq!(run(&function, 5, &mut fuel), Some(15));
+
+        // adding the increment to a huge offset wraps around, the same way the addition does
+        let program = parse(&format!("#ip 5\naddi 1 {} 5", usize::MAX)).unwrap();
+        assert!(
+            decompile(&program)
+                .to_string()
+                .contains("    goto L[r1];\n")
+        );
+    }
+
+    /// Pick a number below `n`, using splitmix64 to advance `seed`
+    fn below(seed: &mut u64, n: usize) -> usize {
+        *seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        ((z ^ (z >> 31)) % n as u64) as usize
+    }
+
+    /// Generate a random program, which jumps around a lot
+    fn gen_program(seed: &mut u64) -> Program {
+        let len = 4 + below(seed, 12);
+        let ip_binding = (below(seed, 8) != 0).then(|| below(seed, NUM_REGS));
+        let reg = |seed: &mut u64| match ip_binding {
+            Some(ip) if below(seed, 4) == 0 => ip,
+            _ => below(seed, NUM_REGS),
+        };
+        let code = (0..len)
+            .map(|_| {
+                let opcode = Opcode::ALL[below(seed, 16)];
+                let [a, b] = opcode.operands().map(|kind| match kind {
+                    Operand::Reg => reg(seed),
+                    Operand::Imm | Operand::Unused => below(seed, len + 2),
+                });
+                Instruction {
+                    opcode,
+                    a,
+                    b,
+                    c: reg(seed),
+                }
+            })
+            .collect();
+        Program { ip_binding, code }
+    }
+
+    #[test]
+    fn fuzz_decompiler() {
+        const STEP_LIMIT: usize = 2000;
+        let mut seed = 0xfeed_f00d_dead_beef;
+        let mut compared = 0;
+        let mut structured = 0;
+        for _ in 0..3000 {
+            let program = gen_program(&mut seed);
+            let flat = lift(&program);
+            let function = decompile(&program);
+            if function
+                .body
+                .iter()
+                .any(|stmt| matches!(stmt, Stmt::Loop(..) | Stmt::While(..)))
+            {
+                structured += 1;
+            }
+            if program.ip_binding == Some(0) {
+                continue;
+            }
+            for r0 in [0, 1, 5] {
+                let mut vm = Vm::new(program.clone());
+                vm.regs[0] = r0;
+                let mut steps = 0;
+                let hung = vm.run_with(|_, _| {
+                    steps += 1;
+                    if steps > STEP_LIMIT {
+                        ControlFlow::Break(())
+                    } else {
+                        ControlFlow::Continue(())
+                    }
+                });
+                if hung.is_some() {
+                    continue;
+                }
+                compared += 1;
+                for function in [&flat, &function] {
+                    let mut fuel = STEP_LIMIT * 10;
+                    assert_eq!(
+                        run(function, r0, &mut fuel),
+                        Some(vm.regs[0]),
+                        "decompiled with r0 = {r0}:\n{program}\n{function}"
+                    );
+                }
+            }
+        }
+        assert!(compared > 1000, "only {compared} programs halted");
+        assert!(structured > 100, "only {structured} programs had loops");
+    }
+}
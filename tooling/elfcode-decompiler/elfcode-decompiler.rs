@@ -0,0 +1,56 @@
+// SPDX-FileCopyrightText: 2026 Eli Array Minkoff
+//
+// SPDX-License-Identifier: 0BSD
+
+//! Decompiler for ElfCode programs, as used in 2018 days 19 and 21, which prints them as
+//! Rust-like pseudo-code with loops and `if` statements recovered wherever possible.
+//!
+//! It replaces the AWK and Python scripts that `2018/day19/part2.md` describes, automating the
+//! conversion and label elimination that were done by hand there.
+//!
+//! Build with `aoc-rustc elfcode-decompiler.rs`, then run with the path to an ElfCode program
+//! (defaulting to `input`). With `--flat`, control flow is left as `goto`s and labels, which is
+//! useful for checking how the structure was recovered.
+
+#[path = "../../drop-ins/rust/elfcode.rs"]
+mod elfcode;
+
+mod decompile;
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut flat = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--flat" => flat = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("usage: elfcode-decompiler [--flat] [path]");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let path = path.unwrap_or_else(|| String::from("input"));
+    let program = match std::fs::read_to_string(&path) {
+        Ok(src) => elfcode::parse(&src),
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let program = match program {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if flat {
+        print!("{}", decompile::lift(&program));
+    } else {
+        print!("{}", decompile::decompile(&program));
+    }
+    ExitCode::SUCCESS
+}
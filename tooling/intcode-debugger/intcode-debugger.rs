@@ -0,0 +1,314 @@
+// SPDX-FileCopyrightText: 2026 Eli Array Minkoff
+//
+// SPDX-License-Identifier: 0BSD
+
+//! Interactive debugger for Intcode programs, with gdb-like commands.
+//!
+//! Build with `aoc-rustc intcode-debugger.rs`, then run with the path to an Intcode program as its
+//! only argument (defaulting to `input`), which can be comma-separated values, assembly, or a
+//! snapshot. Run `help` at the prompt for a list of commands.
+
+#[path = "../../drop-ins/rust/intcode.rs"]
+mod intcode;
+
+use intcode::{Breakpoint, Interpreter, State, Watch};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+const HELP: &str = "\
+commands:
+    break <addr>            pause before executing the instruction at <addr>
+    delete <addr>           remove breakpoint and/or watchpoint at <addr>
+    watch <addr> [r|w|rw]   pause after <addr> is read and/or written (default: w)
+    info                    list breakpoints and watchpoints
+    step [n]                execute <n> instructions (default: 1)
+    continue                run until halting, awaiting input, or hitting a breakpoint
+    back [n]                undo the last <n> instructions (default: 1)
+    rewind                  undo instructions until right before the most recent output
+    mem <addr> [len]        show <len> memory locations starting at <addr> (default: 1)
+    set <addr> <val>        set memory location <addr> to <val>
+    regs                    show the instruction pointer and relative base offset
+    disas [addr] [n]        disassemble <n> items starting at <addr> (default: ip, 10)
+    input <values>          queue input - text in ASCII mode, comma-separated numbers otherwise
+    send <values>           queue input as with `input`, then continue
+    ascii                   toggle between ASCII and numeric I/O
+    trace                   toggle logging of executed instructions to stderr
+    save <file>             save a snapshot of the machine state to <file>
+    load <file>             restore the machine state from a snapshot in <file>
+    dot <file>              write a Graphviz control-flow graph of memory to <file>
+    help                    show this message
+    quit                    exit the debugger";
+
+/// Memory to allow the journal used for `back` and `rewind` to use
+const JOURNAL_BUDGET: usize = 64 << 20;
+
+struct Session {
+    interpreter: Interpreter<'static>,
+    inputs: VecDeque<i64>,
+    ascii: bool,
+    state: Option<State>,
+}
+
+impl Session {
+    fn print_output(&self, outputs: Vec<i64>) {
+        if self.ascii {
+            for i in outputs {
+                match i {
+                    0..127 => print!("{}", i as u8 as char),
+                    _ => println!("[non-ASCII output: {i}]"),
+                }
+            }
+        } else {
+            for i in outputs {
+                println!("output: {i}");
+            }
+        }
+    }
+
+    fn report_state(&self) {
+        match &self.state {
+            None => println!("paused at ip {}", self.interpreter.ip()),
+            Some(State::Halted) => println!("halted at ip {}", self.interpreter.ip()),
+            Some(State::Awaiting) => println!("awaiting input at ip {}", self.interpreter.ip()),
+            Some(State::Break(Breakpoint::Address(addr))) => println!("breakpoint at {addr}"),
+            Some(State::Break(Breakpoint::Read { ip, addr })) => {
+                println!("watchpoint: instruction at {ip} read {addr}")
+            }
+            Some(State::Break(Breakpoint::Write { ip, addr, old, new })) => {
+                println!("watchpoint: instruction at {ip} changed {addr} from {old} to {new}")
+            }
+        }
+    }
+
+    fn queue_input(&mut self, args: &str) -> Result<(), String> {
+        if self.ascii {
+            self.inputs.extend(args.bytes().map(i64::from));
+            self.inputs.push_back(i64::from(b'\n'));
+        } else {
+            for val in args.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                self.inputs
+                    .push_back(val.parse().map_err(|_| format!("invalid input {val:?}"))?);
+            }
+        }
+        Ok(())
+    }
+
+    fn cont(&mut self) -> Result<(), String> {
+        let inputs = &mut self.inputs;
+        let (outputs, state) = self
+            .interpreter
+            .run_through_inputs(std::iter::from_fn(|| inputs.pop_front()))
+            .map_err(|e| format!("{e:?}"))?;
+        self.print_output(outputs);
+        self.state = Some(state);
+        self.report_state();
+        Ok(())
+    }
+
+    fn step(&mut self, count: u64) -> Result<(), String> {
+        let mut outputs = Vec::new();
+        for _ in 0..count {
+            let mut input = self.inputs.pop_front();
+            let result = self.interpreter.step(&mut input, &mut outputs);
+            // put back the input if the instruction didn't use it
+            if let Some(i) = input {
+                self.inputs.push_front(i);
+            }
+            self.state = result.map_err(|e| format!("{e:?}"))?;
+            if self.state.is_some() {
+                break;
+            }
+        }
+        self.print_output(outputs);
+        self.report_state();
+        Ok(())
+    }
+
+    fn run_command(&mut self, line: &str) -> Result<bool, String> {
+        fn num<T: std::str::FromStr>(arg: Option<&str>, name: &str) -> Result<T, String> {
+            let arg = arg.ok_or_else(|| format!("missing {name}"))?;
+            arg.parse().map_err(|_| format!("invalid {name} {arg:?}"))
+        }
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let mut args = rest.split_whitespace();
+
+        match cmd {
+            "" => (),
+            "break" | "b" => {
+                let addr = num(args.next(), "address")?;
+                self.interpreter.add_breakpoint(addr);
+            }
+            "delete" | "d" => {
+                let addr = num(args.next(), "address")?;
+                let had_break = self.interpreter.remove_breakpoint(addr);
+                if !had_break && self.interpreter.remove_watchpoint(addr).is_none() {
+                    return Err(format!("nothing set at {addr}"));
+                }
+            }
+            "watch" | "w" => {
+                let addr = num(args.next(), "address")?;
+                let watch = match args.next().unwrap_or("w") {
+                    "r" => Watch::Read,
+                    "w" => Watch::Write,
+                    "rw" => Watch::Access,
+                    kind => return Err(format!("unknown watch kind {kind:?}")),
+                };
+                self.interpreter.add_watchpoint(addr, watch);
+            }
+            "info" | "i" => {
+                for addr in self.interpreter.breakpoints() {
+                    println!("breakpoint at {addr}");
+                }
+                for (addr, watch) in self.interpreter.watchpoints() {
+                    println!("watchpoint ({watch:?}) on {addr}");
+                }
+                println!("queued input: {:?}", self.inputs);
+            }
+            "step" | "s" => {
+                let count = args.next().map_or(Ok(1), |n| num(Some(n), "count"))?;
+                self.step(count)?;
+            }
+            "continue" | "c" => self.cont()?,
+            "back" => {
+                let count = args.next().map_or(Ok(1), |n| num(Some(n), "count"))?;
+                let undone = self.interpreter.step_back(count);
+                println!("undid {undone} instruction(s)");
+                self.state = None;
+                self.report_state();
+            }
+            "rewind" => {
+                let Some(undone) = self.interpreter.rewind_to_last_output() else {
+                    return Err(String::from("no output in the journal"));
+                };
+                println!("undid {undone} instruction(s)");
+                self.state = None;
+                self.report_state();
+            }
+            "mem" | "x" => {
+                let addr: u64 = num(args.next(), "address")?;
+                let len: u64 = args.next().map_or(Ok(1), |n| num(Some(n), "length"))?;
+                for (i, a) in (addr..addr.saturating_add(len)).enumerate() {
+                    if i % 8 == 0 {
+                        if i > 0 {
+                            println!();
+                        }
+                        print!("{a:>8}:");
+                    }
+                    print!(" {:>8}", self.interpreter.mem_get(a));
+                }
+                println!();
+            }
+            "set" => {
+                let addr = num(args.next(), "address")?;
+                let val = num(args.next(), "value")?;
+                self.interpreter.mem_override(addr, val);
+            }
+            "regs" | "r" => {
+                println!("ip:  {}", self.interpreter.ip());
+                println!("rbo: {}", self.interpreter.rbo());
+            }
+            "disas" => {
+                let addr = args
+                    .next()
+                    .map_or(Ok(self.interpreter.ip()), |a| num(Some(a), "address"))?;
+                let count = args.next().map_or(Ok(10), |n| num(Some(n), "count"))?;
+                let disassembly = self.interpreter.disassemble([addr]);
+                for (a, item) in disassembly
+                    .items()
+                    .skip_while(|&(a, _)| a < addr)
+                    .take(count)
+                {
+                    let text = match item {
+                        intcode::disasm::Item::Code(instr) => disassembly.fmt_instruction(&instr),
+                        intcode::disasm::Item::Data(val) => format!("data({val})"),
+                    };
+                    let marker = if a == self.interpreter.ip() {
+                        "=>"
+                    } else {
+                        "  "
+                    };
+                    println!("{marker} {a:>8}: {text}");
+                }
+            }
+            "input" => self.queue_input(rest)?,
+            "send" => {
+                self.queue_input(rest)?;
+                self.cont()?;
+            }
+            "ascii" => {
+                self.ascii = !self.ascii;
+                println!("ASCII I/O {}", if self.ascii { "on" } else { "off" });
+            }
+            "trace" => {
+                if self.interpreter.stop_tracing().is_none() {
+                    self.interpreter.enable_logging();
+                    println!("tracing on");
+                } else {
+                    println!("tracing off");
+                }
+            }
+            "save" => {
+                let mut file = File::create(rest).map_err(|e| format!("{rest}: {e}"))?;
+                self.interpreter
+                    .save_snapshot(&mut file)
+                    .map_err(|e| format!("{rest}: {e}"))?;
+            }
+            "load" => {
+                let mut file = File::open(rest).map_err(|e| format!("{rest}: {e}"))?;
+                self.interpreter
+                    .restore_snapshot(&mut file)
+                    .map_err(|e| format!("{rest}: {e:?}"))?;
+                self.state = None;
+                self.report_state();
+            }
+            "dot" => {
+                let dot = self.interpreter.cfg([]).to_dot(None);
+                std::fs::write(rest, dot).map_err(|e| format!("{rest}: {e}"))?;
+            }
+            "help" | "h" => println!("{HELP}"),
+            "quit" | "q" => return Ok(false),
+            _ => return Err(format!("unknown command {cmd:?} - try `help`")),
+        }
+        Ok(true)
+    }
+}
+
+fn main() -> ExitCode {
+    use std::env::args;
+    let path = args().nth(1).unwrap_or_else(|| String::from("input"));
+    let mut interpreter = match intcode::load::from_path(&path) {
+        Ok(interpreter) => interpreter,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    interpreter.enable_journal(JOURNAL_BUDGET);
+    let mut session = Session {
+        interpreter,
+        inputs: VecDeque::new(),
+        ascii: true,
+        state: None,
+    };
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(intcode) ");
+        io::stdout().flush().expect("failed to flush stdout");
+        let Some(Ok(line)) = lines.next() else {
+            println!();
+            break;
+        };
+        match session.run_command(&line) {
+            Ok(true) => (),
+            Ok(false) => break,
+            Err(e) => println!("error: {e}"),
+        }
+    }
+    ExitCode::SUCCESS
+}